@@ -1,11 +1,12 @@
 use anyhow::{bail, Result};
-use turbo_tasks::{ValueToString, Vc};
+use turbo_tasks::{Value, ValueToString, Vc};
 use turbo_tasks_fs::FileSystemPath;
 use turbopack_core::{
     chunk::{EvaluatableAsset, EvaluatableAssetExt, EvaluatableAssets},
     context::AssetContext,
     issue::IssueSeverity,
     module::Module,
+    reference_type::CommonJsReferenceSubType,
     resolve::{origin::PlainResolveOrigin, parse::Request},
     source::Source,
 };
@@ -36,6 +37,7 @@ impl RuntimeEntry {
         let modules = cjs_resolve(
             Vc::upcast(PlainResolveOrigin::new(asset_context, path)),
             request,
+            Value::new(CommonJsReferenceSubType::Undefined),
             None,
             IssueSeverity::Error.cell(),
         )
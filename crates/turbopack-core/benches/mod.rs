@@ -0,0 +1,8 @@
+use criterion::{criterion_group, criterion_main};
+
+mod reference_type;
+mod resolve;
+
+criterion_group!(reference_type_benches, reference_type::benchmark);
+criterion_group!(resolve_benches, resolve::benchmark);
+criterion_main!(reference_type_benches, resolve_benches);
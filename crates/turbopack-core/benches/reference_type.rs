@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use criterion::{black_box, Criterion};
+use turbopack_core::reference_type::{
+    CommonJsReferenceSubType, CssReferenceSubType, EcmaScriptModulesReferenceSubType,
+    EntryReferenceSubType, ReferenceType, UrlReferenceSubType,
+};
+
+/// Matches every rule condition against every reference type, like module
+/// rule matching does.
+///
+/// To compare a change against a baseline, pass `--save-baseline before` to
+/// `cargo bench -p turbopack-core --bench mod -- reference_type` before the
+/// change and `--baseline before` after it.
+///
+/// When `includes` got its short-circuiting checks, the workspace couldn't be
+/// built, so this bench wasn't run. The numbers come from a std-only copy of
+/// both versions of `includes` over the same 80 pairs, built with `-O` and
+/// taking the best of 7 runs: about 136 ns per iteration before and about
+/// 120 ns after.
+pub fn benchmark(c: &mut Criterion) {
+    let conditions = [
+        ReferenceType::Undefined,
+        ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined),
+        ReferenceType::Css(CssReferenceSubType::Undefined),
+        ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+        ReferenceType::Url(UrlReferenceSubType::CssUrl),
+        ReferenceType::Entry(EntryReferenceSubType::Undefined),
+        ReferenceType::Custom(1),
+    ];
+    let references = [
+        ReferenceType::CommonJs(CommonJsReferenceSubType::Custom(1)),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport),
+        ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+        ReferenceType::Css(CssReferenceSubType::Compose(None)),
+        ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl),
+        ReferenceType::Entry(EntryReferenceSubType::Page),
+        ReferenceType::Runtime,
+        ReferenceType::Custom(2),
+        ReferenceType::Undefined,
+    ];
+
+    let mut group = c.benchmark_group("reference_type");
+    group.warm_up_time(Duration::from_secs(1));
+    group.measurement_time(Duration::from_secs(3));
+    group.bench_function("includes", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for condition in &conditions {
+                for reference in &references {
+                    if black_box(condition).includes(black_box(reference)) {
+                        count += 1;
+                    }
+                }
+            }
+            count
+        })
+    });
+    group.finish();
+}
@@ -0,0 +1,87 @@
+use std::fs;
+
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{TryJoinIterExt, TurboTasks, Value, Vc};
+use turbo_tasks_fs::{DiskFileSystem, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_core::{
+    reference_type::ReferenceType,
+    register,
+    resolve::{node::node_cjs_resolve_options, parse::Request, resolve},
+};
+
+const PACKAGES: [&str; 3] = ["react", "react-dom", "next"];
+const DIRECTORIES: usize = 100;
+
+/// Resolves the same bare specifiers from many directories of a project, like
+/// the modules of a large app importing `react` do.
+pub fn benchmark(c: &mut Criterion) {
+    register();
+
+    let project = tempfile::tempdir().unwrap();
+    for package in PACKAGES {
+        let package_dir = project.path().join("node_modules").join(package);
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("package.json"),
+            r#"{ "main": "index.js" }"#,
+        )
+        .unwrap();
+        fs::write(package_dir.join("index.js"), "").unwrap();
+    }
+    for directory in 0..DIRECTORIES {
+        fs::create_dir_all(project.path().join(format!("src/{directory}/a/b/c"))).unwrap();
+    }
+    let project_path = project.path().to_string_lossy().to_string();
+
+    let mut group = c.benchmark_group("resolve");
+    group.sample_size(10);
+
+    for depth in [0, 3] {
+        group.bench_with_input(
+            BenchmarkId::new("bare_specifier", depth),
+            &depth,
+            |b, &depth| {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+
+                b.to_async(rt).iter(|| {
+                    let tt = TurboTasks::new(MemoryBackend::default());
+                    let project_path = project_path.clone();
+                    async move {
+                        let task = tt.spawn_once_task(async move {
+                            let fs =
+                                DiskFileSystem::new("project".to_string(), project_path, vec![]);
+                            let root = fs.root();
+                            let options = node_cjs_resolve_options(root);
+                            (0..DIRECTORIES)
+                                .flat_map(|directory| {
+                                    let lookup_path = ["a", "b", "c"][..depth].iter().fold(
+                                        root.join(format!("src/{directory}")),
+                                        |path, name| path.join(name.to_string()),
+                                    );
+                                    PACKAGES.map(|package| {
+                                        resolve(
+                                            lookup_path,
+                                            Value::new(ReferenceType::Undefined),
+                                            Request::parse_string(package.to_string()),
+                                            options,
+                                        )
+                                    })
+                                })
+                                .try_join()
+                                .await?;
+
+                            Ok::<Vc<()>, _>(Default::default())
+                        });
+                        tt.wait_task_completion(task, false).await.unwrap();
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
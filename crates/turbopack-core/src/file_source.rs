@@ -14,6 +14,9 @@ use crate::{
 pub struct FileSource {
     pub path: Vc<FileSystemPath>,
     pub query: Vc<String>,
+    /// The fragment of the request including the `#`, e.g. `#icon`, or an
+    /// empty string.
+    pub fragment: Vc<String>,
 }
 
 #[turbo_tasks::value_impl]
@@ -23,20 +26,44 @@ impl FileSource {
         Self::cell(FileSource {
             path,
             query: Vc::<String>::default(),
+            fragment: Vc::<String>::default(),
         })
     }
 
     #[turbo_tasks::function]
     pub fn new_with_query(path: Vc<FileSystemPath>, query: Vc<String>) -> Vc<Self> {
-        Self::cell(FileSource { path, query })
+        Self::cell(FileSource {
+            path,
+            query,
+            fragment: Vc::<String>::default(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    pub fn new_with_query_and_fragment(
+        path: Vc<FileSystemPath>,
+        query: Vc<String>,
+        fragment: Vc<String>,
+    ) -> Vc<Self> {
+        Self::cell(FileSource {
+            path,
+            query,
+            fragment,
+        })
     }
 }
 
 #[turbo_tasks::value_impl]
 impl Source for FileSource {
     #[turbo_tasks::function]
-    fn ident(&self) -> Vc<AssetIdent> {
-        AssetIdent::from_path(self.path).with_query(self.query)
+    async fn ident(&self) -> Result<Vc<AssetIdent>> {
+        let ident = AssetIdent::from_path(self.path).with_query(self.query);
+        let fragment = self.fragment.await?;
+        // The fragment of an ident doesn't include the `#`.
+        Ok(match fragment.strip_prefix('#') {
+            Some(fragment) => ident.with_fragment(Vc::cell(fragment.to_string())),
+            None => ident,
+        })
     }
 }
 
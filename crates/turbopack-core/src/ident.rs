@@ -129,6 +129,13 @@ impl AssetIdent {
         Self::new(Value::new(this))
     }
 
+    #[turbo_tasks::function]
+    pub fn with_fragment(&self, fragment: Vc<String>) -> Vc<Self> {
+        let mut this = self.clone();
+        this.fragment = Some(fragment);
+        Self::new(Value::new(this))
+    }
+
     #[turbo_tasks::function]
     pub fn with_modifier(&self, modifier: Vc<String>) -> Vc<Self> {
         let mut this = self.clone();
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use turbo_tasks::{TryJoinIterExt, Vc};
+use turbo_tasks_fs::FileSystemPath;
+
+use super::{Issue, IssueSeverity, IssueStage, OptionStyledString, StyledString};
+
+/// A stylesheet which imports itself, directly or through other stylesheets.
+#[turbo_tasks::value(shared)]
+pub struct ImportCycleIssue {
+    /// The stylesheet with the `@import` which closes the cycle.
+    pub file_path: Vc<FileSystemPath>,
+    /// The stylesheets of the cycle in import order, starting and ending with
+    /// the stylesheet which imports itself.
+    pub cycle: Vec<Vc<FileSystemPath>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ImportCycleIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.into()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::ProcessModule.into()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text("Circular @import".into()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<Vc<OptionStyledString>> {
+        let cycle = self
+            .cycle
+            .iter()
+            .map(|path| async move { Ok(path.await?.path.clone()) })
+            .try_join()
+            .await?;
+        Ok(Vc::cell(Some(
+            StyledString::Line(vec![
+                StyledString::Text("The stylesheets ".to_string()),
+                StyledString::Code(cycle.join(" -> ")),
+                StyledString::Text(
+                    " import each other in a cycle. The conditions of the @import which closes \
+                     the cycle are ignored."
+                        .to_string(),
+                ),
+            ])
+            .cell(),
+        )))
+    }
+}
@@ -1,5 +1,6 @@
 pub mod analyze;
 pub mod code_gen;
+pub mod import_cycle;
 pub mod resolve;
 pub mod unsupported_module;
 
@@ -1,10 +1,25 @@
-use std::fmt::Display;
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+    hash::{Hash, Hasher},
+    sync::RwLock,
+};
 
-use anyhow::Result;
-use indexmap::IndexMap;
-use turbo_tasks::Vc;
+use anyhow::{bail, Result};
+use indexmap::{IndexMap, IndexSet};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use turbo_tasks::{debug::ValueDebugFormat, trace::TraceRawVcs, TaskInput, ValueToString, Vc};
+use turbo_tasks_fs::FileSystemPath;
+use turbo_tasks_hash::{encode_hex, DeterministicHash, DeterministicHasher, Xxh3Hash64Hasher};
 
-use crate::{module::Module, resolve::ModulePart};
+use crate::{
+    issue::{import_cycle::ImportCycleIssue, IssueExt},
+    module::{Module, OptionModule},
+    resolve::ModulePart,
+};
 
 /// Named references to inner assets. Modules can used them to allow to
 /// per-module aliases of some requests to already created module assets.
@@ -18,6 +33,69 @@ impl InnerAssets {
     pub fn empty() -> Vc<Self> {
         Vc::cell(IndexMap::new())
     }
+
+    /// Returns a new map with `module` added as `name`. An existing entry with
+    /// the same name is overwritten, keeping its position.
+    #[turbo_tasks::function]
+    pub async fn with_asset(
+        self: Vc<Self>,
+        name: String,
+        module: Vc<Box<dyn Module>>,
+    ) -> Result<Vc<Self>> {
+        let mut assets = self.await?.clone_value();
+        assets.insert(name, module);
+        Ok(Vc::cell(assets))
+    }
+
+    /// Merges two maps. Modules in `b` take precedence over modules with the
+    /// same name in `a`. The result keeps the order of `a`, followed by the
+    /// names which only exist in `b`.
+    #[turbo_tasks::function]
+    pub async fn merge(a: Vc<InnerAssets>, b: Vc<InnerAssets>) -> Result<Vc<Self>> {
+        let mut assets = a.await?.clone_value();
+        assets.extend(
+            b.await?
+                .iter()
+                .map(|(name, module)| (name.clone(), *module)),
+        );
+        Ok(Vc::cell(assets))
+    }
+
+    /// Returns the module named `name`, if any.
+    #[turbo_tasks::function]
+    pub async fn get(self: Vc<Self>, name: String) -> Result<Vc<OptionModule>> {
+        Ok(Vc::cell(self.await?.get(&name).copied()))
+    }
+
+    /// Returns true if there is a module named `name`.
+    #[turbo_tasks::function]
+    pub async fn contains_key(self: Vc<Self>, name: String) -> Result<Vc<bool>> {
+        Ok(Vc::cell(self.await?.contains_key(&name)))
+    }
+
+    /// Returns a copy of the map with the names in lexical order. The order
+    /// of a map depends on the order in which the modules were added, which
+    /// can vary between runs, so this should be used when the inner assets
+    /// are part of an ident or a content hash. Lookups don't depend on the
+    /// order, so the original map can be used for everything else.
+    #[turbo_tasks::function]
+    pub async fn sorted(self: Vc<Self>) -> Result<Vc<Self>> {
+        let mut assets = self.await?.clone_value();
+        assets.sort_keys();
+        Ok(Vc::cell(assets))
+    }
+
+    /// Returns the number of modules.
+    #[turbo_tasks::function]
+    pub async fn len(self: Vc<Self>) -> Result<Vc<usize>> {
+        Ok(Vc::cell(self.await?.len()))
+    }
+
+    /// Returns true if there are no modules.
+    #[turbo_tasks::function]
+    pub async fn is_empty(self: Vc<Self>) -> Result<Vc<bool>> {
+        Ok(Vc::cell(self.await?.is_empty()))
+    }
 }
 
 // These enums list well-known types, which we use internally. Plugins might add
@@ -26,9 +104,57 @@ impl InnerAssets {
 // TODO when plugins are supported, replace u8 with a trait that defines the
 // behavior.
 
+/// Human-readable names for `Custom(u8)` reference types, registered by the
+/// plugins which define them.
+static CUSTOM_REFERENCE_NAMES: Lazy<RwLock<HashMap<u8, &'static str>>> =
+    Lazy::new(Default::default);
+
+/// Registers a human-readable name for the custom reference type `id`, which
+/// is used when displaying a `ReferenceType::Custom(id)`.
+///
+/// Each id can only be named once. Registering the same name again is a no-op,
+/// registering a different name for an already named id is an error.
+pub fn register_custom_reference_name(id: u8, name: &'static str) -> Result<()> {
+    let mut names = CUSTOM_REFERENCE_NAMES.write().unwrap();
+    match names.entry(id) {
+        Entry::Occupied(entry) => {
+            if *entry.get() != name {
+                bail!(
+                    "custom reference type {id} is already registered as \"{}\", can't register \
+                     it as \"{name}\"",
+                    entry.get()
+                );
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(name);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name registered for the custom reference type `id`, if any.
+fn custom_reference_name(id: u8) -> Option<&'static str> {
+    CUSTOM_REFERENCE_NAMES.read().unwrap().get(&id).copied()
+}
+
+/// Returns the id of the custom reference type registered as `name`, if any.
+fn custom_reference_id(name: &str) -> Option<u8> {
+    CUSTOM_REFERENCE_NAMES
+        .read()
+        .unwrap()
+        .iter()
+        .find_map(|(id, registered)| (*registered == name).then_some(*id))
+}
+
 #[turbo_tasks::value(serialization = "auto_for_input")]
 #[derive(Debug, Clone, PartialOrd, Ord, Hash)]
 pub enum CommonJsReferenceSubType {
+    /// `require("x")`, which needs the referenced module.
+    Require,
+    /// `require.resolve("x")`, which only needs the id of the referenced
+    /// module.
+    RequireResolve,
     Custom(u8),
     Undefined,
 }
@@ -39,34 +165,655 @@ pub enum EcmaScriptModulesReferenceSubType {
     ImportPart(Vc<ModulePart>),
     Import,
     DynamicImport,
+    /// `export * from "..."`
+    ExportAll,
+    /// `export { x } from "..."`
+    ReExport,
+    /// A reference created by a property of `import.meta`.
+    ImportMeta(ImportMetaProperty),
     Custom(u8),
     #[default]
     Undefined,
 }
 
+/// The property of `import.meta` of an
+/// [EcmaScriptModulesReferenceSubType::ImportMeta] reference.
+#[derive(
+    TaskInput,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    TraceRawVcs,
+    ValueDebugFormat,
+)]
+pub enum ImportMetaProperty {
+    /// `import.meta.url`, which needs to be rewritten to the URL of the module
+    /// in the output.
+    Url,
+    /// `import.meta.resolve("x")`, which only needs the URL of the referenced
+    /// module, like `require.resolve`.
+    Resolve,
+}
+
+impl EcmaScriptModulesReferenceSubType {
+    /// Creates an `ImportPart` sub type for the whole module, e.g. for a
+    /// namespace import. The part is resolved, so all such sub types compare
+    /// equal.
+    pub async fn import_part_all() -> Result<Self> {
+        Ok(EcmaScriptModulesReferenceSubType::ImportPart(
+            ModulePart::exports().resolve().await?,
+        ))
+    }
+}
+
+/// The cascade layer of an `@import`.
+#[derive(
+    TaskInput,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    TraceRawVcs,
+    ValueDebugFormat,
+    DeterministicHash,
+)]
+pub enum ImportLayer {
+    /// No `layer`, the module is not wrapped in a layer.
+    #[default]
+    None,
+    /// A bare `layer`, which wraps the module in a new anonymous layer.
+    Anonymous,
+    /// `layer(name)`, which wraps the module in the named layer.
+    Named(String),
+}
+
+impl ImportLayer {
+    /// Creates an [ImportLayer::Named], e.g. `ImportLayer::named("base")`.
+    pub fn named(name: impl Into<String>) -> ImportLayer {
+        ImportLayer::Named(name.into())
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, ImportLayer::None)
+    }
+}
+
 /// The individual set of conditions present on this module through `@import`
-#[derive(Debug)]
+#[derive(Debug, Hash)]
 #[turbo_tasks::value(shared)]
 pub struct ImportAttributes {
-    pub layer: Option<String>,
+    pub layer: ImportLayer,
     pub supports: Option<String>,
     pub media: Option<String>,
 }
 
+impl ImportAttributes {
+    /// Parses the prelude of an `@import` rule, e.g.
+    /// `@import "x.css" layer(a) supports(display: grid) screen;`. The leading
+    /// `@import` and the trailing semicolon are optional, the url is not.
+    ///
+    /// The `supports` condition is the content of the `supports()` function
+    /// and the media is the remaining media query list, both kept verbatim.
+    pub fn parse(raw: &str) -> Result<ImportAttributes> {
+        let mut rest = raw.trim();
+        rest = rest.strip_suffix(';').unwrap_or(rest).trim_end();
+        if let Some(after_import) = strip_prefix_ignore_ascii_case(rest, "@import") {
+            rest = after_import.trim_start();
+        }
+
+        rest = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => split_quoted(rest, quote)?.1,
+            _ => match strip_prefix_ignore_ascii_case(rest, "url") {
+                Some(after_url) if after_url.starts_with('(') => split_parens(after_url)?.1,
+                _ => bail!("expected a url at the start of the @import prelude: {raw}"),
+            },
+        }
+        .trim_start();
+
+        let mut layer = ImportLayer::None;
+        if let Some(after_layer) = strip_prefix_ignore_ascii_case(rest, "layer") {
+            if after_layer.starts_with('(') {
+                let (name, after_name) = split_parens(after_layer)?;
+                let name = name.trim();
+                if name.is_empty() {
+                    bail!("empty layer name in @import prelude: {raw}");
+                }
+                layer = ImportLayer::Named(name.to_string());
+                rest = after_name.trim_start();
+            } else if after_layer.is_empty() || after_layer.starts_with(char::is_whitespace) {
+                layer = ImportLayer::Anonymous;
+                rest = after_layer.trim_start();
+            }
+        }
+
+        let mut supports = None;
+        if let Some(after_supports) = strip_prefix_ignore_ascii_case(rest, "supports") {
+            if after_supports.starts_with('(') {
+                let (condition, after_condition) = split_parens(after_supports)?;
+                supports = Some(condition.trim().to_string());
+                rest = after_condition.trim_start();
+            }
+        }
+
+        Ok(ImportAttributes {
+            layer,
+            supports,
+            media: (!rest.is_empty()).then(|| rest.to_string()),
+        })
+    }
+}
+
+fn strip_prefix_ignore_ascii_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+/// Splits a string starting with `quote` into the content of the string and
+/// the rest after the closing quote.
+fn split_quoted(s: &str, quote: char) -> Result<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return Ok((&s[1..i], &s[i + 1..])),
+            _ => {}
+        }
+    }
+    bail!("unterminated string in @import prelude: {s}")
+}
+
+/// Splits a string starting with `(` into the content between the matching
+/// parentheses and the rest after the closing one. Nested parentheses and
+/// strings are skipped.
+fn split_parens(s: &str) -> Result<(&str, &str)> {
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((&s[1..i], &s[i + 1..]));
+                }
+            }
+            '"' | '\'' => {
+                let (_, rest) = split_quoted(&s[i..], c)?;
+                i = s.len() - rest.len();
+                continue;
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    bail!("unbalanced parentheses in @import prelude: {s}")
+}
+
 /// The accumulated list of conditions that should be applied to this module
 /// through its import path
+///
+/// Two contexts are equal when they have the same layers, media queries and
+/// supports conditions in the same order, so identical import chains share
+/// the same `AtImport` reference sub type.
 #[derive(Debug, Default)]
 #[turbo_tasks::value]
 pub struct ImportContext {
-    pub layers: Vec<String>,
+    /// The layers of the import chain, outermost first. Never contains
+    /// [ImportLayer::None].
+    pub layers: Vec<ImportLayer>,
     pub supports: Vec<String>,
     pub media: Vec<String>,
 }
 
+impl ImportContext {
+    /// Like [ImportContext::new], but accepts anything which converts into a
+    /// string as conditions, e.g. `ImportContext::from_conditions(vec![],
+    /// ["print"], [])`.
+    pub fn from_conditions(
+        layers: Vec<ImportLayer>,
+        media: impl IntoIterator<Item = impl Into<String>>,
+        supports: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Vc<ImportContext> {
+        ImportContext::new(
+            layers,
+            media.into_iter().map(Into::into).collect(),
+            supports.into_iter().map(Into::into).collect(),
+        )
+    }
+
+    /// Returns true if no conditions have been accumulated.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty() && self.media.is_empty() && self.supports.is_empty()
+    }
+
+    /// Returns the number of accumulated conditions, i.e. layers, supports
+    /// conditions and media queries together.
+    pub fn len(&self) -> usize {
+        self.layers.len() + self.supports.len() + self.media.len()
+    }
+
+    /// Returns the accumulated conditions with their dimension, in the order
+    /// of [ImportContext::wrappers]: layers, supports conditions and then
+    /// media queries, each outermost first. Anonymous layers have an empty
+    /// name.
+    pub fn iter(&self) -> impl Iterator<Item = (ImportDimension, &str)> {
+        let layers = self.layers.iter().map(|layer| match layer {
+            ImportLayer::Named(name) => (ImportDimension::Layer, name.as_str()),
+            ImportLayer::None | ImportLayer::Anonymous => (ImportDimension::Layer, ""),
+        });
+        let supports = self
+            .supports
+            .iter()
+            .map(|condition| (ImportDimension::Supports, condition.as_str()));
+        let media = self
+            .media
+            .iter()
+            .map(|query| (ImportDimension::Media, query.as_str()));
+        layers.chain(supports).chain(media)
+    }
+
+    /// Returns a new context with the attributes of a nested `@import`
+    /// appended.
+    ///
+    /// Conditions are kept in import nesting order, outermost first, so the
+    /// result only depends on the import chain and not on the order in which
+    /// the import graph is traversed. Media queries and supports conditions
+    /// are combined with `and`, so a condition which is already present in the
+    /// chain is not added again. Layers are always added, since a layer nested
+    /// in a layer with the same name is a different layer.
+    pub fn with_attributes(
+        &self,
+        attr_layer: ImportLayer,
+        attr_media: Option<String>,
+        attr_supports: Option<String>,
+    ) -> ImportContext {
+        fn append_unique(list: &[String], item: Option<String>) -> Vec<String> {
+            let mut list = list.to_vec();
+            if let Some(item) = item {
+                if !list.contains(&item) {
+                    list.push(item);
+                }
+            }
+            list
+        }
+
+        let mut layers = self.layers.clone();
+        if !attr_layer.is_none() {
+            layers.push(attr_layer);
+        }
+
+        ImportContext {
+            layers,
+            media: append_unique(&self.media, attr_media),
+            supports: append_unique(&self.supports, attr_supports),
+        }
+    }
+
+    /// Returns a new context with the attributes of an outer `@import`
+    /// prepended. This is the counterpart of [ImportContext::with_attributes]
+    /// for callers which walk the import chain from the inside out, and
+    /// results in the same context for the same chain.
+    pub fn with_attributes_outer(
+        &self,
+        attr_layer: ImportLayer,
+        attr_media: Option<String>,
+        attr_supports: Option<String>,
+    ) -> ImportContext {
+        fn prepend_unique(list: &[String], item: Option<String>) -> Vec<String> {
+            let Some(item) = item else {
+                return list.to_vec();
+            };
+            // Only the outermost occurrence is kept, like when appending.
+            std::iter::once(item.clone())
+                .chain(list.iter().filter(|existing| **existing != item).cloned())
+                .collect()
+        }
+
+        let mut layers = Vec::with_capacity(self.layers.len() + 1);
+        if !attr_layer.is_none() {
+            layers.push(attr_layer);
+        }
+        layers.extend(self.layers.iter().cloned());
+
+        ImportContext {
+            layers,
+            media: prepend_unique(&self.media, attr_media),
+            supports: prepend_unique(&self.supports, attr_supports),
+        }
+    }
+
+    /// Returns the nested `@layer` blocks the module needs to be wrapped in,
+    /// outermost first. Consecutive named layers are combined into a single
+    /// dotted name. Anonymous layers can't be combined and are returned as
+    /// they are.
+    pub fn layer_blocks(&self) -> Vec<ImportLayer> {
+        let mut blocks: Vec<ImportLayer> = Vec::new();
+        for layer in &self.layers {
+            match (blocks.last_mut(), layer) {
+                (Some(ImportLayer::Named(block)), ImportLayer::Named(name)) => {
+                    block.push('.');
+                    block.push_str(name);
+                }
+                (_, ImportLayer::None) => {}
+                _ => blocks.push(layer.clone()),
+            }
+        }
+        blocks
+    }
+
+    /// Returns the names of all named layers used by `contexts`, in order of
+    /// their first appearance. This is the order in which the layers are
+    /// declared when the modules are concatenated in the same order, so it can
+    /// be used for an upfront `@layer` statement. Layers nested in anonymous
+    /// layers can't be referenced and are omitted.
+    pub fn layer_order<'a>(contexts: impl IntoIterator<Item = &'a ImportContext>) -> Vec<String> {
+        let mut order: Vec<String> = Vec::new();
+        for context in contexts {
+            if let Some(ImportLayer::Named(name)) = context.layer_blocks().into_iter().next() {
+                if !order.contains(&name) {
+                    order.push(name);
+                }
+            }
+        }
+        order
+    }
+
+    /// Combines the accumulated media query lists into a single media query
+    /// list, or returns `None` if there are none.
+    ///
+    /// Nested media query lists match when both match, so each query of the
+    /// outer list is combined with each query of the inner list using `and`.
+    /// Combinations of different media types can never match and are
+    /// dropped, which results in `not all` if no query is left. Negated media
+    /// queries like `not print` can't be combined and result in an error.
+    pub fn media_query(&self) -> Result<Option<String>> {
+        let mut combined: Option<Vec<MediaQuery>> = None;
+        for media in &self.media {
+            let queries = split_top_level(media, |c| c == ',')
+                .map(MediaQuery::parse)
+                .collect::<Result<Vec<_>>>()?;
+            combined = Some(match combined {
+                None => queries,
+                Some(outer) => outer
+                    .iter()
+                    .flat_map(|outer| queries.iter().filter_map(|inner| outer.and(inner)))
+                    .collect(),
+            });
+        }
+        Ok(combined.map(|queries| {
+            if queries.is_empty() {
+                "not all".to_string()
+            } else {
+                queries
+                    .iter()
+                    .map(|query| query.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        }))
+    }
+
+    /// Combines the accumulated supports conditions into a single condition
+    /// for an `@supports` rule, or returns `None` if there are none. Each
+    /// condition is wrapped in parentheses, unless it already is or it's a
+    /// single supports function like `selector(a > b)`, and they are combined
+    /// with `and`.
+    pub fn supports_query(&self) -> Option<String> {
+        if self.supports.is_empty() {
+            return None;
+        }
+        Some(
+            self.supports
+                .iter()
+                .map(|condition| wrap_supports_condition(condition.trim()))
+                .collect::<Vec<_>>()
+                .join(" and "),
+        )
+    }
+
+    /// Returns the at-rules the rules of the module need to be wrapped in when
+    /// the import chain is flattened, outermost first.
+    ///
+    /// Layers are outermost, followed by the supports condition and the media
+    /// query, which is the order of the conditions of an `@import` rule.
+    pub fn wrappers(&self) -> Result<Vec<ImportWrapper>> {
+        let mut wrappers = self
+            .layer_blocks()
+            .into_iter()
+            .map(ImportWrapper::Layer)
+            .collect::<Vec<_>>();
+        if let Some(supports) = self.supports_query() {
+            wrappers.push(ImportWrapper::Supports(supports));
+        }
+        if let Some(media) = self.media_query()? {
+            wrappers.push(ImportWrapper::Media(media));
+        }
+        Ok(wrappers)
+    }
+}
+
+/// The kind of a condition accumulated in an [ImportContext].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImportDimension {
+    /// A `layer` or `layer(name)`.
+    Layer,
+    /// A `supports(condition)`.
+    Supports,
+    /// A media query list.
+    Media,
+}
+
+/// An at-rule which the rules of a module are wrapped in when an `@import`
+/// with conditions is flattened. See [ImportContext::wrappers].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportWrapper {
+    /// `@layer name { ... }`, or `@layer { ... }` for an anonymous layer.
+    /// Never [ImportLayer::None].
+    Layer(ImportLayer),
+    /// `@supports condition { ... }`.
+    Supports(String),
+    /// `@media query-list { ... }`.
+    Media(String),
+}
+
+/// Formats the prelude of the at-rule, without the block, e.g. `@media print`.
+impl Display for ImportWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportWrapper::Layer(ImportLayer::Named(name)) => write!(f, "@layer {name}"),
+            ImportWrapper::Layer(_) => f.write_str("@layer"),
+            ImportWrapper::Supports(condition) => write!(f, "@supports {condition}"),
+            ImportWrapper::Media(query) => write!(f, "@media {query}"),
+        }
+    }
+}
+
+/// A single query of a media query list, split into its media type and the
+/// conditions which need to match.
+#[derive(Clone)]
+struct MediaQuery<'a> {
+    media_type: Option<&'a str>,
+    conditions: Vec<String>,
+}
+
+impl<'a> MediaQuery<'a> {
+    fn parse(query: &'a str) -> Result<Self> {
+        let words = split_top_level(query, char::is_whitespace).collect::<Vec<_>>();
+        let mut rest = &words[..];
+        if let [only, after_only @ ..] = rest {
+            if only.eq_ignore_ascii_case("only") {
+                rest = after_only;
+            }
+        }
+        let mut media_type = None;
+        if let [first, after_first @ ..] = rest {
+            if !first.starts_with('(') {
+                if first.eq_ignore_ascii_case("not") {
+                    if !after_first
+                        .first()
+                        .is_some_and(|word| word.starts_with('('))
+                    {
+                        bail!("negated media queries can't be combined: {query}");
+                    }
+                } else {
+                    media_type = Some(*first);
+                    rest = match after_first {
+                        [] => after_first,
+                        [and, after_and @ ..] if and.eq_ignore_ascii_case("and") => after_and,
+                        _ => bail!("invalid media query: {query}"),
+                    };
+                }
+            }
+        }
+
+        let is_conjunction = rest
+            .iter()
+            .all(|word| !word.eq_ignore_ascii_case("or") && !word.eq_ignore_ascii_case("not"));
+        let conditions = if is_conjunction {
+            rest.iter()
+                .filter(|word| !word.eq_ignore_ascii_case("and"))
+                .map(|word| word.to_string())
+                .collect()
+        } else {
+            // `or` and `not` conditions are kept as a whole.
+            vec![rest.join(" ")]
+        };
+        Ok(MediaQuery {
+            media_type: media_type.filter(|media_type| !media_type.eq_ignore_ascii_case("all")),
+            conditions,
+        })
+    }
+
+    /// Returns a query which matches when both queries match, or `None` if
+    /// they can never match at the same time.
+    fn and(&self, other: &Self) -> Option<Self> {
+        let media_type = match (self.media_type, other.media_type) {
+            (Some(a), Some(b)) if !a.eq_ignore_ascii_case(b) => return None,
+            (a, b) => a.or(b),
+        };
+        let mut conditions = self.conditions.clone();
+        for condition in &other.conditions {
+            if !conditions.contains(condition) {
+                conditions.push(condition.clone());
+            }
+        }
+        Some(MediaQuery {
+            media_type,
+            conditions,
+        })
+    }
+}
+
+impl Display for MediaQuery<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = self
+            .media_type
+            .into_iter()
+            .map(Cow::Borrowed)
+            .collect::<Vec<_>>();
+        if self.media_type.is_none() && self.conditions.len() == 1 {
+            parts.push(Cow::Borrowed(self.conditions[0].as_str()));
+        } else {
+            parts.extend(
+                self.conditions
+                    .iter()
+                    .map(|condition| wrap_in_parens(condition)),
+            );
+        }
+        if parts.is_empty() {
+            f.write_str("all")
+        } else {
+            f.write_str(&parts.join(" and "))
+        }
+    }
+}
+
+/// Splits `s` at all `separator` characters which aren't nested in
+/// parentheses. Empty parts are skipped.
+fn split_top_level(s: &str, separator: impl Fn(char) -> bool) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    s.split(move |c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        depth == 0 && separator(c)
+    })
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+}
+
+/// Wraps `condition` in parentheses, unless it's already wrapped as a whole.
+fn wrap_in_parens(condition: &str) -> Cow<'_, str> {
+    let is_wrapped = condition.starts_with('(')
+        && matches!(split_parens(condition), Ok((_, rest)) if rest.trim().is_empty());
+    if is_wrapped {
+        Cow::Borrowed(condition)
+    } else {
+        Cow::Owned(format!("({condition})"))
+    }
+}
+
+/// Wraps a supports condition in parentheses, so it can be combined with
+/// others. Supports functions like `selector(a > b)` can be combined as they
+/// are, so they are kept, like conditions which are already wrapped.
+fn wrap_supports_condition(condition: &str) -> Cow<'_, str> {
+    let name_len = condition.find('(').unwrap_or_default();
+    let name = &condition[..name_len];
+    let is_function = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && matches!(split_parens(&condition[name_len..]), Ok((_, rest)) if rest.trim().is_empty());
+    if is_function {
+        Cow::Borrowed(condition)
+    } else {
+        wrap_in_parens(condition)
+    }
+}
+
 #[turbo_tasks::value_impl]
 impl ImportContext {
     #[turbo_tasks::function]
-    pub fn new(layers: Vec<String>, media: Vec<String>, supports: Vec<String>) -> Vc<Self> {
+    pub fn new(layers: Vec<ImportLayer>, media: Vec<String>, supports: Vec<String>) -> Vc<Self> {
+        // Like `with_attributes`, only the first occurrence of a media or
+        // supports condition is kept. Layers can repeat, since each one is
+        // nested in the previous ones.
+        fn unique(list: &[String]) -> Vec<String> {
+            list.iter()
+                .cloned()
+                .collect::<IndexSet<_>>()
+                .into_iter()
+                .collect()
+        }
+
+        let canonical_layers = layers
+            .iter()
+            .filter(|layer| !layer.is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        let canonical_media = unique(&media);
+        let canonical_supports = unique(&supports);
+        if canonical_layers != layers || canonical_media != media || canonical_supports != supports
+        {
+            // Equal contexts share the same cell, so they compare equal.
+            return ImportContext::new(canonical_layers, canonical_media, canonical_supports);
+        }
+
         ImportContext {
             layers,
             media,
@@ -78,43 +825,183 @@ impl ImportContext {
     #[turbo_tasks::function]
     pub async fn add_attributes(
         self: Vc<Self>,
-        attr_layer: Option<String>,
+        attr_layer: ImportLayer,
         attr_media: Option<String>,
         attr_supports: Option<String>,
     ) -> Result<Vc<Self>> {
-        let this = &*self.await?;
+        if attr_layer.is_none() && attr_media.is_none() && attr_supports.is_none() {
+            return Ok(self);
+        }
 
-        let layers = {
-            let mut layers = this.layers.clone();
-            if let Some(attr_layer) = attr_layer {
-                if !layers.contains(&attr_layer) {
-                    layers.push(attr_layer.to_owned());
-                }
-            }
-            layers
-        };
+        // Go through `ImportContext::new` so equal contexts share the same
+        // cell, regardless of the import chain which created them.
+        let context = self
+            .await?
+            .with_attributes(attr_layer, attr_media, attr_supports);
+        Ok(ImportContext::new(
+            context.layers,
+            context.media,
+            context.supports,
+        ))
+    }
 
-        let media = {
-            let mut media = this.media.clone();
-            if let Some(attr_media) = attr_media {
-                if !media.contains(&attr_media) {
-                    media.push(attr_media.to_owned());
-                }
+    /// Like `add_attributes`, but for an `@import` of `stylesheet` by the last
+    /// of `importers`, the stylesheets of the import chain, outermost first.
+    /// When `stylesheet` is already part of the chain, it imports itself,
+    /// directly or through other stylesheets, so adding the attributes on
+    /// every round of the cycle would never end. Instead, the context is
+    /// returned unchanged and an [ImportCycleIssue] is emitted. A stylesheet
+    /// which is imported through multiple chains, e.g. by both sides of a
+    /// diamond, is not a cycle.
+    #[turbo_tasks::function]
+    pub async fn add_attributes_in_chain(
+        self: Vc<Self>,
+        importers: Vec<Vc<FileSystemPath>>,
+        stylesheet: Vc<FileSystemPath>,
+        attr_layer: ImportLayer,
+        attr_media: Option<String>,
+        attr_supports: Option<String>,
+    ) -> Result<Vc<Self>> {
+        let stylesheet_value = stylesheet.await?;
+        let mut cycle_start = None;
+        for (index, importer) in importers.iter().enumerate() {
+            if *importer.await? == *stylesheet_value {
+                cycle_start = Some(index);
+                break;
             }
-            media
+        }
+        let (Some(cycle_start), Some(&importer)) = (cycle_start, importers.last()) else {
+            return Ok(self.add_attributes(attr_layer, attr_media, attr_supports));
         };
 
-        let supports = {
-            let mut supports = this.supports.clone();
-            if let Some(attr_supports) = attr_supports {
-                if !supports.contains(&attr_supports) {
-                    supports.push(attr_supports.to_owned());
-                }
-            }
-            supports
-        };
+        ImportCycleIssue {
+            file_path: importer,
+            cycle: importers[cycle_start..]
+                .iter()
+                .copied()
+                .chain(std::iter::once(stylesheet))
+                .collect(),
+        }
+        .cell()
+        .emit();
+        Ok(self)
+    }
+
+    /// Like `add_attributes`, but for the attributes of an outer `@import`.
+    /// See [ImportContext::with_attributes_outer].
+    #[turbo_tasks::function]
+    pub async fn add_attributes_outer(
+        self: Vc<Self>,
+        attr_layer: ImportLayer,
+        attr_media: Option<String>,
+        attr_supports: Option<String>,
+    ) -> Result<Vc<Self>> {
+        if attr_layer.is_none() && attr_media.is_none() && attr_supports.is_none() {
+            return Ok(self);
+        }
+
+        let context = self
+            .await?
+            .with_attributes_outer(attr_layer, attr_media, attr_supports);
+        Ok(ImportContext::new(
+            context.layers,
+            context.media,
+            context.supports,
+        ))
+    }
+
+    /// Returns the context without any layers, e.g. to emit the layers of a
+    /// module separately from its other conditions.
+    #[turbo_tasks::function]
+    pub async fn without_layer(self: Vc<Self>) -> Result<Vc<Self>> {
+        let this = self.await?;
+        if this.layers.is_empty() {
+            return Ok(self);
+        }
+        Ok(ImportContext::new(
+            vec![],
+            this.media.clone(),
+            this.supports.clone(),
+        ))
+    }
+
+    /// Returns the context without any media queries.
+    #[turbo_tasks::function]
+    pub async fn without_media(self: Vc<Self>) -> Result<Vc<Self>> {
+        let this = self.await?;
+        if this.media.is_empty() {
+            return Ok(self);
+        }
+        Ok(ImportContext::new(
+            this.layers.clone(),
+            vec![],
+            this.supports.clone(),
+        ))
+    }
+
+    /// Returns the context without any supports conditions.
+    #[turbo_tasks::function]
+    pub async fn without_supports(self: Vc<Self>) -> Result<Vc<Self>> {
+        let this = self.await?;
+        if this.supports.is_empty() {
+            return Ok(self);
+        }
+        Ok(ImportContext::new(
+            this.layers.clone(),
+            this.media.clone(),
+            vec![],
+        ))
+    }
 
-        Ok(ImportContext::new(layers, media, supports))
+    /// Combines the accumulated media query lists into a single media query
+    /// list. See [ImportContext::media_query].
+    #[turbo_tasks::function]
+    pub async fn to_media_query(self: Vc<Self>) -> Result<Vc<Option<String>>> {
+        Ok(Vc::cell(self.await?.media_query()?))
+    }
+
+    /// Combines the accumulated supports conditions into a single condition.
+    /// See [ImportContext::supports_query].
+    #[turbo_tasks::function]
+    pub async fn to_supports_query(self: Vc<Self>) -> Result<Vc<Option<String>>> {
+        Ok(Vc::cell(self.await?.supports_query()))
+    }
+
+    /// Returns a deterministic key of all conditions, which can be part of the
+    /// content hash of flattened CSS. Like the equality of contexts, the key
+    /// depends on the order of the conditions. An empty context has a key
+    /// too.
+    #[turbo_tasks::function]
+    pub async fn cache_key(self: Vc<Self>) -> Result<Vc<String>> {
+        let this = self.await?;
+        let mut hasher = Xxh3Hash64Hasher::new();
+        // Vectors are hashed with their length, so conditions can't move
+        // between them without changing the key.
+        this.layers.deterministic_hash(&mut hasher);
+        this.supports.deterministic_hash(&mut hasher);
+        this.media.deterministic_hash(&mut hasher);
+        Ok(Vc::cell(encode_hex(hasher.finish())))
+    }
+}
+
+/// The classes a CSS Modules `composes: ... from "..."` declaration composes
+/// from the referenced module.
+///
+/// `composes: a from global` doesn't reference another module, so it has no
+/// compose target.
+#[derive(Debug, Default)]
+#[turbo_tasks::value]
+pub struct ComposeTarget {
+    /// The composed class names in declaration order, e.g. `a` and `b` for
+    /// `composes: a b from "./other.css"`.
+    pub class_names: Vec<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl ComposeTarget {
+    #[turbo_tasks::function]
+    pub fn new(class_names: Vec<String>) -> Vc<Self> {
+        ComposeTarget { class_names }.cell()
     }
 }
 
@@ -122,7 +1009,30 @@ impl ImportContext {
 #[derive(Debug, Clone, PartialOrd, Ord, Hash)]
 pub enum CssReferenceSubType {
     AtImport(Option<Vc<ImportContext>>),
-    Compose,
+    /// An `AtImport` whose import context only has layers, e.g. for
+    /// `@import "x.css" layer(a)`. This avoids an [ImportContext] cell for
+    /// the common case. [CssReferenceSubType::at_import] creates it instead of
+    /// an `AtImport` with an equivalent context, so the two are never mixed.
+    AtImportLayerOnly(Vec<ImportLayer>),
+    /// An `AtImport` written with the `url()` function, e.g.
+    /// `@import url(x.css)`. It resolves like `@import "x.css"` and is only
+    /// kept separate, so the original syntax can be preserved when the
+    /// stylesheet is emitted. [ReferenceType::includes] treats it like an
+    /// `AtImport` with the same import context. Contexts with only layers
+    /// are kept as they are, see [CssReferenceSubType::at_import_url].
+    AtImportUrl(Option<Vc<ImportContext>>),
+    /// A Sass `@use` rule with the namespace given by its `as` clause, e.g.
+    /// `Some("math")` for `@use "sass:math" as math` and `Some("*")` for
+    /// `as *`. Without an `as` clause, the namespace is derived from the URL.
+    /// Unlike an `@import`, the module is only loaded once and its members
+    /// are only visible in the namespace, so it never includes `AtImport`.
+    Use(Option<String>),
+    /// A Sass `@forward` rule with the prefix given by its `as` clause, e.g.
+    /// `Some("list-")` for `@forward "list" as list-*`.
+    Forward(Option<String>),
+    /// A CSS Modules `composes` reference. Without a target, it matches
+    /// compose references to any classes.
+    Compose(Option<Vc<ComposeTarget>>),
     /// Reference from any asset to a CSS-parseable asset.
     ///
     /// This marks the boundary between non-CSS and CSS assets. The Next.js App
@@ -133,15 +1043,100 @@ pub enum CssReferenceSubType {
     Undefined,
 }
 
+impl CssReferenceSubType {
+    /// Creates an `AtImport` sub type for the given accumulated import context.
+    /// Empty contexts are omitted, so imports without any conditions share the
+    /// same `AtImport(None)` value, and contexts with only layers become
+    /// `AtImportLayerOnly`. Other contexts are resolved, so contexts created by
+    /// [ImportContext::new] with the same conditions compare equal.
+    pub async fn at_import(import_context: Vc<ImportContext>) -> Result<Self> {
+        let context = import_context.await?;
+        Ok(if context.is_empty() {
+            CssReferenceSubType::AtImport(None)
+        } else if context.media.is_empty() && context.supports.is_empty() {
+            CssReferenceSubType::AtImportLayerOnly(context.layers.clone())
+        } else {
+            CssReferenceSubType::AtImport(Some(import_context.resolve().await?))
+        })
+    }
+
+    /// Like [CssReferenceSubType::at_import], but for the `url()` form of
+    /// `@import`. Contexts with only layers don't have a separate sub type,
+    /// so [ReferenceType::includes] can't match them with an
+    /// `AtImportLayerOnly`, but [ReferenceType::includes_exact] does.
+    pub async fn at_import_url(import_context: Vc<ImportContext>) -> Result<Self> {
+        Ok(CssReferenceSubType::AtImportUrl(
+            if import_context.await?.is_empty() {
+                None
+            } else {
+                Some(import_context.resolve().await?)
+            },
+        ))
+    }
+
+    /// Returns the import context of an `AtImport`, `AtImportLayerOnly` or
+    /// `AtImportUrl` sub type, or `None` if there are no conditions or this is
+    /// another sub type.
+    pub fn import_context(&self) -> Option<Vc<ImportContext>> {
+        match self {
+            CssReferenceSubType::AtImport(context) | CssReferenceSubType::AtImportUrl(context) => {
+                *context
+            }
+            CssReferenceSubType::AtImportLayerOnly(layers) => {
+                Some(ImportContext::new(layers.clone(), vec![], vec![]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Creates a `Compose` sub type for the given target. The target is
+    /// resolved, so targets created by [ComposeTarget::new] with the same
+    /// class names compare equal.
+    pub async fn compose(target: Vc<ComposeTarget>) -> Result<Self> {
+        Ok(CssReferenceSubType::Compose(Some(target.resolve().await?)))
+    }
+}
+
 #[turbo_tasks::value(serialization = "auto_for_input")]
 #[derive(Debug, Clone, PartialOrd, Ord, Hash)]
 pub enum UrlReferenceSubType {
     EcmaScriptNewUrl,
     CssUrl,
+    /// A `new URL(...)` passed to a `Worker` constructor, e.g.
+    /// `new Worker(new URL("./worker.js", import.meta.url))`. The referenced
+    /// module is the entry of a separate chunk group.
+    Worker,
+    /// A resource hint, e.g. `<link rel="preload">`, which asks the browser to
+    /// fetch the referenced module before it's used.
+    ResourceHint(ResourceHint),
     Custom(u8),
     Undefined,
 }
 
+/// The kind of a [UrlReferenceSubType::ResourceHint].
+#[derive(
+    TaskInput,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    TraceRawVcs,
+    ValueDebugFormat,
+)]
+pub enum ResourceHint {
+    /// `<link rel="preload">`: the module is needed by the current page.
+    Preload,
+    /// `<link rel="prefetch">`: the module is likely needed by a future
+    /// navigation, so it's fetched with a low priority.
+    Prefetch,
+}
+
 #[turbo_tasks::value(serialization = "auto_for_input")]
 #[derive(Debug, Clone, PartialOrd, Ord, Hash)]
 pub enum TypeScriptReferenceSubType {
@@ -149,6 +1144,64 @@ pub enum TypeScriptReferenceSubType {
     Undefined,
 }
 
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, PartialOrd, Ord, Hash)]
+pub enum JsonReferenceSubType {
+    /// Like `ImportWithAttributes`, but for a part of the importing module,
+    /// like [EcmaScriptModulesReferenceSubType::ImportPart].
+    ImportPartWithAttributes(Vc<ModulePart>),
+    /// `import data from "./data.json" with { type: "json" }`, or the older
+    /// `assert { type: "json" }` form.
+    ImportWithAttributes,
+    /// `require("./data.json")`
+    Require,
+    Custom(u8),
+    Undefined,
+}
+
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, PartialOrd, Ord, Hash)]
+pub enum WebAssemblyReferenceSubType {
+    /// `import { add } from "./m.wasm"`, which instantiates the module
+    /// synchronously from the importer's point of view. The importer becomes
+    /// an async module.
+    Sync,
+    /// `import init from "./m.wasm"`, which exports a function instantiating
+    /// the module asynchronously.
+    Async,
+    /// `import url from "./m.wasm?url"`, which only references the emitted
+    /// asset.
+    Url,
+    /// `import module from "./m.wasm?module"`, which exports the compiled
+    /// `WebAssembly.Module` without instantiating it.
+    Module,
+    Custom(u8),
+    Undefined,
+}
+
+impl WebAssemblyReferenceSubType {
+    /// Returns the sub type selected by the query of a request, e.g. `?url` or
+    /// `?module`, or `None` if the query doesn't select one. The query
+    /// includes the leading `?`, and other query parameters are ignored.
+    pub fn from_query(query: &str) -> Option<Self> {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let mut result = None;
+        for param in query.split('&') {
+            let sub_type = match param {
+                "url" => WebAssemblyReferenceSubType::Url,
+                "module" => WebAssemblyReferenceSubType::Module,
+                _ => continue,
+            };
+            // `?url&module` is ambiguous, so it doesn't select either.
+            if matches!(&result, Some(result) if *result != sub_type) {
+                return None;
+            }
+            result = Some(sub_type);
+        }
+        result
+    }
+}
+
 // TODO(sokra) this was next.js specific values. We want to solve this in a
 // different way.
 #[turbo_tasks::value(serialization = "auto_for_input")]
@@ -160,9 +1213,17 @@ pub enum EntryReferenceSubType {
     AppPage,
     AppRoute,
     AppClientComponent,
+    /// A module with server actions, which are exposed to the client.
+    ServerAction,
+    /// A React Server Component.
+    ServerComponent,
     Middleware,
     Instrumentation,
     Runtime,
+    /// An entry kind introduced by an embedder. Named entries only include
+    /// entries with the same name, and never one of the variants above, even
+    /// if the name matches its display name.
+    Named(String),
     Custom(u8),
     Undefined,
 }
@@ -175,76 +1236,557 @@ pub enum ReferenceType {
     Css(CssReferenceSubType),
     Url(UrlReferenceSubType),
     TypeScript(TypeScriptReferenceSubType),
+    Json(JsonReferenceSubType),
+    WebAssembly(WebAssemblyReferenceSubType),
     Entry(EntryReferenceSubType),
     Runtime,
+    /// A reference to a manifest generated by a framework, e.g. a build
+    /// manifest. Manifests are internal and are excluded from the processing
+    /// of regular modules.
+    Manifest,
     Internal(Vc<InnerAssets>),
     Custom(u8),
     Undefined,
 }
 
+/// The top-level category of a [ReferenceType], without its sub type.
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, Hash)]
+pub enum ReferenceCategory {
+    CommonJs,
+    EcmaScriptModules,
+    Css,
+    Url,
+    TypeScript,
+    Json,
+    WebAssembly,
+    Entry,
+    Runtime,
+    Manifest,
+    Internal,
+    Custom,
+    Undefined,
+}
+
+/// Feeds a [Hash] implementation into a [DeterministicHasher].
+struct StableHasher<'a>(&'a mut Xxh3Hash64Hasher);
+
+impl Hasher for StableHasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write_bytes(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
 impl Display for ReferenceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO print sub types
         let str = match self {
             ReferenceType::CommonJs(_) => "commonjs",
-            ReferenceType::EcmaScriptModules(sub) => match sub {
-                EcmaScriptModulesReferenceSubType::ImportPart(_) => "EcmaScript Modules (part)",
-                _ => "EcmaScript Modules",
-            },
+            ReferenceType::EcmaScriptModules(_) => "EcmaScript Modules",
             ReferenceType::Css(_) => "css",
             ReferenceType::Url(_) => "url",
             ReferenceType::TypeScript(_) => "typescript",
+            ReferenceType::Json(_) => "json",
+            ReferenceType::WebAssembly(_) => "wasm",
             ReferenceType::Entry(_) => "entry",
             ReferenceType::Runtime => "runtime",
+            ReferenceType::Manifest => "manifest",
             ReferenceType::Internal(_) => "internal",
-            ReferenceType::Custom(_) => todo!(),
+            ReferenceType::Custom(id) => {
+                return match custom_reference_name(*id) {
+                    Some(name) => write!(f, "custom({name})"),
+                    None => write!(f, "custom({id})"),
+                };
+            }
             ReferenceType::Undefined => "undefined",
         };
-        f.write_str(str)
+        f.write_str(str)?;
+        // Named entries are displayed with the name given by the embedder.
+        if let ReferenceType::Entry(EntryReferenceSubType::Named(name)) = self {
+            write!(f, " ({name})")?;
+        } else if let Some(sub_type) = self.sub_type_name() {
+            write!(f, " ({sub_type})")?;
+        }
+        Ok(())
     }
 }
 
 impl ReferenceType {
+    /// Creates a `ReferenceType::Custom(id)` and registers `name` as its
+    /// human-readable name. See [`register_custom_reference_name`].
+    pub fn custom_with_name(id: u8, name: &'static str) -> Result<Self> {
+        register_custom_reference_name(id, name)?;
+        Ok(ReferenceType::Custom(id))
+    }
+
+    /// Returns a short human-readable name of the sub type, or `None` if this
+    /// reference type has no sub types.
+    pub fn sub_type_name(&self) -> Option<&'static str> {
+        Some(match self {
+            ReferenceType::CommonJs(sub_type) => match sub_type {
+                CommonJsReferenceSubType::Require => "require",
+                CommonJsReferenceSubType::RequireResolve => "require.resolve",
+                CommonJsReferenceSubType::Custom(_) => "custom",
+                CommonJsReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::EcmaScriptModules(sub_type) => match sub_type {
+                EcmaScriptModulesReferenceSubType::ImportPart(_) => "part",
+                EcmaScriptModulesReferenceSubType::Import => "import",
+                EcmaScriptModulesReferenceSubType::DynamicImport => "dynamic import",
+                EcmaScriptModulesReferenceSubType::ExportAll => "export all",
+                EcmaScriptModulesReferenceSubType::ReExport => "re-export",
+                EcmaScriptModulesReferenceSubType::ImportMeta(ImportMetaProperty::Url) => {
+                    "import.meta.url"
+                }
+                EcmaScriptModulesReferenceSubType::ImportMeta(ImportMetaProperty::Resolve) => {
+                    "import.meta.resolve"
+                }
+                EcmaScriptModulesReferenceSubType::Custom(_) => "custom",
+                EcmaScriptModulesReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::Css(sub_type) => match sub_type {
+                CssReferenceSubType::AtImport(_) => "at-import",
+                CssReferenceSubType::AtImportLayerOnly(_) => "at-import layer",
+                CssReferenceSubType::AtImportUrl(_) => "at-import url",
+                CssReferenceSubType::Use(_) => "use",
+                CssReferenceSubType::Forward(_) => "forward",
+                CssReferenceSubType::Compose(_) => "compose",
+                CssReferenceSubType::Internal => "internal",
+                CssReferenceSubType::Custom(_) => "custom",
+                CssReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::Url(sub_type) => match sub_type {
+                UrlReferenceSubType::EcmaScriptNewUrl => "new URL",
+                UrlReferenceSubType::CssUrl => "css url()",
+                UrlReferenceSubType::Worker => "worker",
+                UrlReferenceSubType::ResourceHint(ResourceHint::Preload) => "preload",
+                UrlReferenceSubType::ResourceHint(ResourceHint::Prefetch) => "prefetch",
+                UrlReferenceSubType::Custom(_) => "custom",
+                UrlReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::TypeScript(sub_type) => match sub_type {
+                TypeScriptReferenceSubType::Custom(_) => "custom",
+                TypeScriptReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::Json(sub_type) => match sub_type {
+                JsonReferenceSubType::ImportPartWithAttributes(_) => "part with attributes",
+                JsonReferenceSubType::ImportWithAttributes => "import with attributes",
+                JsonReferenceSubType::Require => "require",
+                JsonReferenceSubType::Custom(_) => "custom",
+                JsonReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::WebAssembly(sub_type) => match sub_type {
+                WebAssemblyReferenceSubType::Sync => "sync",
+                WebAssemblyReferenceSubType::Async => "async",
+                WebAssemblyReferenceSubType::Url => "url",
+                WebAssemblyReferenceSubType::Module => "module",
+                WebAssemblyReferenceSubType::Custom(_) => "custom",
+                WebAssemblyReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::Entry(sub_type) => match sub_type {
+                EntryReferenceSubType::Web => "web",
+                EntryReferenceSubType::Page => "page",
+                EntryReferenceSubType::PagesApi => "pages api",
+                EntryReferenceSubType::AppPage => "app page",
+                EntryReferenceSubType::AppRoute => "app route",
+                EntryReferenceSubType::AppClientComponent => "app client component",
+                EntryReferenceSubType::ServerAction => "server action",
+                EntryReferenceSubType::ServerComponent => "server component",
+                EntryReferenceSubType::Middleware => "middleware",
+                EntryReferenceSubType::Instrumentation => "instrumentation",
+                EntryReferenceSubType::Runtime => "runtime",
+                EntryReferenceSubType::Named(_) => "named",
+                EntryReferenceSubType::Custom(_) => "custom",
+                EntryReferenceSubType::Undefined => "undefined",
+            },
+            ReferenceType::Runtime
+            | ReferenceType::Manifest
+            | ReferenceType::Internal(_)
+            | ReferenceType::Custom(_)
+            | ReferenceType::Undefined => return None,
+        })
+    }
+
     pub fn includes(&self, other: &Self) -> bool {
-        if self == other {
+        // This is called for every rule condition and every reference, so the
+        // cheap checks come first. None of them allocates or reads a `Vc`.
+        if matches!(self, ReferenceType::Undefined) || self == other {
             return true;
         }
+        if self.category() != other.category() {
+            return false;
+        }
+        if self.has_undefined_sub_type() {
+            return true;
+        }
+        match (self, other) {
+            // An `AtImport` without an import context matches any `AtImport`,
+            // including layer only and `url()` ones. Otherwise the import
+            // contexts need to be the same, which is covered by the equality
+            // check above.
+            (
+                ReferenceType::Css(
+                    CssReferenceSubType::AtImport(None) | CssReferenceSubType::AtImportUrl(None),
+                ),
+                ReferenceType::Css(
+                    CssReferenceSubType::AtImport(_)
+                    | CssReferenceSubType::AtImportLayerOnly(_)
+                    | CssReferenceSubType::AtImportUrl(_),
+                ),
+            )
+            | (
+                ReferenceType::Css(
+                    CssReferenceSubType::AtImport(_)
+                    | CssReferenceSubType::AtImportLayerOnly(_)
+                    | CssReferenceSubType::AtImportUrl(_),
+                ),
+                ReferenceType::Css(
+                    CssReferenceSubType::AtImport(None) | CssReferenceSubType::AtImportUrl(None),
+                ),
+            ) => true,
+            // The syntax of an `@import` doesn't matter.
+            (
+                ReferenceType::Css(CssReferenceSubType::AtImport(context)),
+                ReferenceType::Css(CssReferenceSubType::AtImportUrl(other_context)),
+            )
+            | (
+                ReferenceType::Css(CssReferenceSubType::AtImportUrl(context)),
+                ReferenceType::Css(CssReferenceSubType::AtImport(other_context)),
+            ) => context == other_context,
+            // Same for a `Compose` without a target.
+            (
+                ReferenceType::Css(CssReferenceSubType::Compose(target)),
+                ReferenceType::Css(CssReferenceSubType::Compose(other_target)),
+            ) => target.is_none() || other_target.is_none(),
+            (ReferenceType::Internal(_), ReferenceType::Internal(_)) => true,
+            // `Use` and `Forward` only include themselves, since their
+            // namespaces change how the members of the module are linked.
+            // Custom reference types are opaque to us, so they only include
+            // custom reference types with the same id. Mismatched ids never
+            // include each other.
+            _ => false,
+        }
+    }
+
+    /// Returns true if the sub type of this reference type is `Undefined`, so
+    /// it includes all reference types of the same category.
+    fn has_undefined_sub_type(&self) -> bool {
         match self {
             ReferenceType::CommonJs(sub_type) => {
-                matches!(other, ReferenceType::CommonJs(_))
-                    && matches!(sub_type, CommonJsReferenceSubType::Undefined)
+                matches!(sub_type, CommonJsReferenceSubType::Undefined)
             }
             ReferenceType::EcmaScriptModules(sub_type) => {
-                matches!(other, ReferenceType::EcmaScriptModules(_))
-                    && matches!(sub_type, EcmaScriptModulesReferenceSubType::Undefined)
+                matches!(sub_type, EcmaScriptModulesReferenceSubType::Undefined)
             }
-            ReferenceType::Css(CssReferenceSubType::AtImport(_)) => {
-                // For condition matching, treat any AtImport pair as identical.
-                matches!(other, ReferenceType::Css(CssReferenceSubType::AtImport(_)))
+            ReferenceType::Css(sub_type) => matches!(sub_type, CssReferenceSubType::Undefined),
+            ReferenceType::Url(sub_type) => matches!(sub_type, UrlReferenceSubType::Undefined),
+            ReferenceType::TypeScript(sub_type) => {
+                matches!(sub_type, TypeScriptReferenceSubType::Undefined)
             }
-            ReferenceType::Css(sub_type) => {
-                matches!(other, ReferenceType::Css(_))
-                    && matches!(sub_type, CssReferenceSubType::Undefined)
+            ReferenceType::Json(sub_type) => matches!(sub_type, JsonReferenceSubType::Undefined),
+            ReferenceType::WebAssembly(sub_type) => {
+                matches!(sub_type, WebAssemblyReferenceSubType::Undefined)
             }
-            ReferenceType::Url(sub_type) => {
-                matches!(other, ReferenceType::Url(_))
-                    && matches!(sub_type, UrlReferenceSubType::Undefined)
+            ReferenceType::Entry(sub_type) => matches!(sub_type, EntryReferenceSubType::Undefined),
+            ReferenceType::Runtime
+            | ReferenceType::Manifest
+            | ReferenceType::Internal(_)
+            | ReferenceType::Custom(_)
+            | ReferenceType::Undefined => false,
+        }
+    }
+
+    /// Like [ReferenceType::includes], but `Internal` reference types only
+    /// include each other when their inner assets match exactly: both need to
+    /// have the same names, and each name needs to refer to the same module.
+    /// The order of the inner assets is not relevant. An `AtImportUrl` whose
+    /// import context only has layers includes the `AtImportLayerOnly` with
+    /// the same layers.
+    pub async fn includes_exact(&self, other: &Self) -> Result<bool> {
+        if let (
+            ReferenceType::Css(CssReferenceSubType::AtImportUrl(Some(context))),
+            ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(layers)),
+        )
+        | (
+            ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(layers)),
+            ReferenceType::Css(CssReferenceSubType::AtImportUrl(Some(context))),
+        ) = (self, other)
+        {
+            let context = context.await?;
+            return Ok(context.media.is_empty()
+                && context.supports.is_empty()
+                && context.layers == *layers);
+        }
+        let (ReferenceType::Internal(assets), ReferenceType::Internal(other_assets)) =
+            (self, other)
+        else {
+            return Ok(self.includes(other));
+        };
+        let assets = assets.await?;
+        let other_assets = other_assets.await?;
+        if assets.len() != other_assets.len() {
+            return Ok(false);
+        }
+        for (name, module) in assets.iter() {
+            let Some(other_module) = other_assets.get(name) else {
+                return Ok(false);
+            };
+            if module.resolve().await? != other_module.resolve().await? {
+                return Ok(false);
             }
-            ReferenceType::TypeScript(sub_type) => {
-                matches!(other, ReferenceType::TypeScript(_))
-                    && matches!(sub_type, TypeScriptReferenceSubType::Undefined)
+        }
+        Ok(true)
+    }
+
+    /// Returns a hash of this reference type which is stable across task runs.
+    /// The derived [Hash] implementation hashes the cells of import contexts,
+    /// compose targets, module parts and inner assets by their identity, while
+    /// this hashes their contents, so equal contents in different cells hash
+    /// equal. Inner assets are hashed in order, by name and module ident.
+    pub async fn stable_hash(&self) -> Result<u64> {
+        let mut hasher = Xxh3Hash64Hasher::new();
+        std::mem::discriminant(self).deterministic_hash(&mut hasher);
+        match self {
+            ReferenceType::EcmaScriptModules(
+                sub_type @ EcmaScriptModulesReferenceSubType::ImportPart(part),
+            ) => {
+                std::mem::discriminant(sub_type).deterministic_hash(&mut hasher);
+                part.to_string().await?.deterministic_hash(&mut hasher);
+            }
+            ReferenceType::Json(
+                sub_type @ JsonReferenceSubType::ImportPartWithAttributes(part),
+            ) => {
+                std::mem::discriminant(sub_type).deterministic_hash(&mut hasher);
+                part.to_string().await?.deterministic_hash(&mut hasher);
             }
-            ReferenceType::Entry(sub_type) => {
-                matches!(other, ReferenceType::Entry(_))
-                    && matches!(sub_type, EntryReferenceSubType::Undefined)
+            ReferenceType::Css(
+                sub_type @ (CssReferenceSubType::AtImport(Some(import_context))
+                | CssReferenceSubType::AtImportUrl(Some(import_context))),
+            ) => {
+                std::mem::discriminant(sub_type).deterministic_hash(&mut hasher);
+                import_context
+                    .cache_key()
+                    .await?
+                    .deterministic_hash(&mut hasher);
             }
-            ReferenceType::Runtime => matches!(other, ReferenceType::Runtime),
-            ReferenceType::Internal(_) => matches!(other, ReferenceType::Internal(_)),
-            ReferenceType::Custom(_) => {
-                todo!()
+            ReferenceType::Css(sub_type @ CssReferenceSubType::Compose(Some(target))) => {
+                std::mem::discriminant(sub_type).deterministic_hash(&mut hasher);
+                target.await?.class_names.deterministic_hash(&mut hasher);
             }
-            ReferenceType::Undefined => true,
+            ReferenceType::Internal(inner_assets) => {
+                let inner_assets = inner_assets.await?;
+                inner_assets.len().deterministic_hash(&mut hasher);
+                for (name, module) in inner_assets.iter() {
+                    name.deterministic_hash(&mut hasher);
+                    module
+                        .ident()
+                        .to_string()
+                        .await?
+                        .deterministic_hash(&mut hasher);
+                }
+            }
+            // Other values don't reference cells, so their derived hash is stable.
+            _ => self.hash(&mut StableHasher(&mut hasher)),
         }
+        Ok(hasher.finish())
+    }
+
+    /// Returns a human-friendly JSON summary of this reference type for
+    /// external tools, e.g. in dumps of the module graph. Unlike the derived
+    /// serialization, it doesn't contain cell ids. The shape is
+    /// `{ "category", "subType", "payload" }`, where `subType` is the
+    /// [sub_type_name](ReferenceType::sub_type_name) and `payload` summarizes
+    /// the contents of the sub type, e.g. the conditions of an import context
+    /// or the names of inner assets. Both are `null` when they are absent.
+    pub async fn to_json_value(&self) -> Result<serde_json::Value> {
+        let category = match self.category() {
+            ReferenceCategory::CommonJs => "commonjs",
+            ReferenceCategory::EcmaScriptModules => "EcmaScript Modules",
+            ReferenceCategory::Css => "css",
+            ReferenceCategory::Url => "url",
+            ReferenceCategory::TypeScript => "typescript",
+            ReferenceCategory::Json => "json",
+            ReferenceCategory::WebAssembly => "wasm",
+            ReferenceCategory::Entry => "entry",
+            ReferenceCategory::Runtime => "runtime",
+            ReferenceCategory::Manifest => "manifest",
+            ReferenceCategory::Internal => "internal",
+            ReferenceCategory::Custom => "custom",
+            ReferenceCategory::Undefined => "undefined",
+        };
+        let layers = |layers: &[ImportLayer]| -> Vec<Option<String>> {
+            layers
+                .iter()
+                .filter_map(|layer| match layer {
+                    ImportLayer::None => None,
+                    ImportLayer::Anonymous => Some(None),
+                    ImportLayer::Named(name) => Some(Some(name.clone())),
+                })
+                .collect()
+        };
+        let payload = match self {
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Custom(id))
+            | ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Custom(id))
+            | ReferenceType::Css(CssReferenceSubType::Custom(id))
+            | ReferenceType::Url(UrlReferenceSubType::Custom(id))
+            | ReferenceType::TypeScript(TypeScriptReferenceSubType::Custom(id))
+            | ReferenceType::Json(JsonReferenceSubType::Custom(id))
+            | ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Custom(id))
+            | ReferenceType::Entry(EntryReferenceSubType::Custom(id)) => json!({ "id": id }),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(
+                part,
+            ))
+            | ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(part)) => {
+                json!({ "part": *part.to_string().await? })
+            }
+            ReferenceType::Css(
+                CssReferenceSubType::AtImport(Some(import_context))
+                | CssReferenceSubType::AtImportUrl(Some(import_context)),
+            ) => {
+                let import_context = import_context.await?;
+                json!({
+                    "layers": layers(&import_context.layers),
+                    "media": import_context.media,
+                    "supports": import_context.supports,
+                })
+            }
+            ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(import_layers)) => {
+                json!({ "layers": layers(import_layers) })
+            }
+            ReferenceType::Css(CssReferenceSubType::Use(namespace)) => {
+                json!({ "namespace": namespace })
+            }
+            ReferenceType::Css(CssReferenceSubType::Forward(prefix)) => {
+                json!({ "prefix": prefix })
+            }
+            ReferenceType::Css(CssReferenceSubType::Compose(Some(target))) => {
+                json!({ "classNames": target.await?.class_names })
+            }
+            ReferenceType::Entry(EntryReferenceSubType::Named(name)) => {
+                json!({ "name": name })
+            }
+            ReferenceType::Internal(inner_assets) => {
+                let names = inner_assets.await?.keys().cloned().collect::<Vec<_>>();
+                json!({ "innerAssets": names })
+            }
+            ReferenceType::Custom(id) => {
+                json!({ "id": id, "name": custom_reference_name(*id) })
+            }
+            _ => serde_json::Value::Null,
+        };
+        Ok(json!({
+            "category": category,
+            "subType": self.sub_type_name(),
+            "payload": payload,
+        }))
+    }
+
+    /// Returns the category of this reference type, dropping the sub type.
+    pub fn category(&self) -> ReferenceCategory {
+        match self {
+            ReferenceType::CommonJs(_) => ReferenceCategory::CommonJs,
+            ReferenceType::EcmaScriptModules(_) => ReferenceCategory::EcmaScriptModules,
+            ReferenceType::Css(_) => ReferenceCategory::Css,
+            ReferenceType::Url(_) => ReferenceCategory::Url,
+            ReferenceType::TypeScript(_) => ReferenceCategory::TypeScript,
+            ReferenceType::Json(_) => ReferenceCategory::Json,
+            ReferenceType::WebAssembly(_) => ReferenceCategory::WebAssembly,
+            ReferenceType::Entry(_) => ReferenceCategory::Entry,
+            ReferenceType::Runtime => ReferenceCategory::Runtime,
+            ReferenceType::Manifest => ReferenceCategory::Manifest,
+            ReferenceType::Internal(_) => ReferenceCategory::Internal,
+            ReferenceType::Custom(_) => ReferenceCategory::Custom,
+            ReferenceType::Undefined => ReferenceCategory::Undefined,
+        }
+    }
+
+    /// Parses the output of [Display] back into a reference type of the same
+    /// category, with an `Undefined` sub type. This is lossy, since sub types
+    /// are dropped: `"EcmaScript Modules (part)"` results in
+    /// `EcmaScriptModules(Undefined)`, which includes the original reference
+    /// type but isn't equal to it. `"internal"` can't be parsed, since it
+    /// needs inner assets. Custom reference types are parsed by id, e.g.
+    /// `"custom(3)"`, or by the name registered with
+    /// [register_custom_reference_name].
+    pub fn from_category_str(s: &str) -> Option<ReferenceType> {
+        let category = match s.split_once(" (") {
+            Some((category, sub_type)) if sub_type.ends_with(')') => category,
+            _ => s,
+        };
+        Some(match category {
+            "commonjs" => ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+            "EcmaScript Modules" => {
+                ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined)
+            }
+            "css" => ReferenceType::Css(CssReferenceSubType::Undefined),
+            "url" => ReferenceType::Url(UrlReferenceSubType::Undefined),
+            "typescript" => ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+            "json" => ReferenceType::Json(JsonReferenceSubType::Undefined),
+            "wasm" => ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Undefined),
+            "entry" => ReferenceType::Entry(EntryReferenceSubType::Undefined),
+            "runtime" => ReferenceType::Runtime,
+            "manifest" => ReferenceType::Manifest,
+            "undefined" => ReferenceType::Undefined,
+            _ => {
+                let name = category.strip_prefix("custom(")?.strip_suffix(')')?;
+                let id = custom_reference_id(name).or_else(|| name.parse().ok())?;
+                ReferenceType::Custom(id)
+            }
+        })
+    }
+
+    /// Returns true if this is a CommonJS reference, regardless of sub type.
+    pub fn is_commonjs(&self) -> bool {
+        matches!(self, ReferenceType::CommonJs(_))
+    }
+
+    /// Returns true if this is an ESM reference, regardless of sub type.
+    pub fn is_ecmascript(&self) -> bool {
+        matches!(self, ReferenceType::EcmaScriptModules(_))
+    }
+
+    /// Returns true if this is a CSS reference, regardless of sub type.
+    pub fn is_css(&self) -> bool {
+        matches!(self, ReferenceType::Css(_))
+    }
+
+    /// Returns true if this is a url reference, regardless of sub type.
+    pub fn is_url(&self) -> bool {
+        matches!(self, ReferenceType::Url(_))
+    }
+
+    /// Returns true if this is a CSS reference or a `url()` in CSS. Unlike
+    /// [ReferenceType::is_css], this also matches the assets referenced by
+    /// CSS files, which are in the url category.
+    pub fn is_css_related(&self) -> bool {
+        matches!(
+            self,
+            ReferenceType::Css(_) | ReferenceType::Url(UrlReferenceSubType::CssUrl)
+        )
+    }
+
+    /// Returns true if this is a TypeScript reference, regardless of sub type.
+    pub fn is_typescript(&self) -> bool {
+        matches!(self, ReferenceType::TypeScript(_))
+    }
+
+    /// Returns true if this is a JSON reference, regardless of sub type.
+    pub fn is_json(&self) -> bool {
+        matches!(self, ReferenceType::Json(_))
+    }
+
+    /// Returns true if this is a WebAssembly reference, regardless of sub
+    /// type.
+    pub fn is_wasm(&self) -> bool {
+        matches!(self, ReferenceType::WebAssembly(_))
+    }
+
+    /// Returns true if this is an entry reference, regardless of sub type.
+    pub fn is_entry(&self) -> bool {
+        matches!(self, ReferenceType::Entry(_))
     }
 
     /// Returns true if this reference type is internal. This will be used in
@@ -256,6 +1798,1220 @@ impl ReferenceType {
             ReferenceType::Internal(_)
                 | ReferenceType::Css(CssReferenceSubType::Internal)
                 | ReferenceType::Runtime
+                | ReferenceType::Manifest
+        )
+    }
+
+    /// Returns the module part of an `ImportPart` or `ImportPartWithAttributes`
+    /// reference, or `None` for all other reference types.
+    pub fn as_module_part(&self) -> Option<Vc<ModulePart>> {
+        match self {
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(
+                part,
+            ))
+            | ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(part)) => {
+                Some(*part)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the inner assets of an `Internal` reference, or `None` for all
+    /// other reference types.
+    pub fn inner_assets(&self) -> Option<Vc<InnerAssets>> {
+        match self {
+            ReferenceType::Internal(inner_assets) => Some(*inner_assets),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the referenced module is loaded asynchronously, so the
+    /// reference introduces an async boundary where chunks can be split.
+    pub fn is_async(&self) -> bool {
+        matches!(
+            self,
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport)
+                | ReferenceType::Url(UrlReferenceSubType::Worker)
+                | ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async)
         )
     }
+
+    /// Returns how specific this reference type is as a rule condition, so the
+    /// most specific of multiple matching conditions can be picked:
+    ///
+    /// - 0 for `Undefined`, which includes all reference types.
+    /// - 1 for a category with an `Undefined` sub type.
+    /// - 2 for other sub types, including custom ones, and for reference types
+    ///   without sub types, e.g. `Runtime` or `Custom`.
+    /// - 3 for sub types which are narrowed down by their value, e.g. an
+    ///   `AtImport` with an import context or a `Compose` with a target.
+    pub fn specificity(&self) -> u32 {
+        match self {
+            ReferenceType::Undefined => 0,
+            _ if self.has_undefined_sub_type() => 1,
+            ReferenceType::Css(
+                CssReferenceSubType::AtImport(Some(_))
+                | CssReferenceSubType::AtImportLayerOnly(_)
+                | CssReferenceSubType::AtImportUrl(Some(_))
+                | CssReferenceSubType::Compose(Some(_)),
+            ) => 3,
+            _ => 2,
+        }
+    }
+
+    /// Returns every reference type which isn't defined by an embedder, with
+    /// one value per sub type. Sub types which carry a value are returned
+    /// without it where possible, e.g. `AtImport(None)`. Custom reference
+    /// types, named entries, `AtImportLayerOnly`, `ImportPart` and `Internal`
+    /// are omitted, since they can't be enumerated.
+    pub fn well_known() -> impl Iterator<Item = ReferenceType> {
+        [
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Require),
+            ReferenceType::CommonJs(CommonJsReferenceSubType::RequireResolve),
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ExportAll),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ReExport),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportMeta(
+                ImportMetaProperty::Url,
+            )),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportMeta(
+                ImportMetaProperty::Resolve,
+            )),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined),
+            ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+            ReferenceType::Css(CssReferenceSubType::AtImportUrl(None)),
+            ReferenceType::Css(CssReferenceSubType::Use(None)),
+            ReferenceType::Css(CssReferenceSubType::Forward(None)),
+            ReferenceType::Css(CssReferenceSubType::Compose(None)),
+            ReferenceType::Css(CssReferenceSubType::Internal),
+            ReferenceType::Css(CssReferenceSubType::Undefined),
+            ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl),
+            ReferenceType::Url(UrlReferenceSubType::CssUrl),
+            ReferenceType::Url(UrlReferenceSubType::Worker),
+            ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Preload)),
+            ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Prefetch)),
+            ReferenceType::Url(UrlReferenceSubType::Undefined),
+            ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+            ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes),
+            ReferenceType::Json(JsonReferenceSubType::Require),
+            ReferenceType::Json(JsonReferenceSubType::Undefined),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Sync),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Url),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Module),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Undefined),
+            ReferenceType::Entry(EntryReferenceSubType::Web),
+            ReferenceType::Entry(EntryReferenceSubType::Page),
+            ReferenceType::Entry(EntryReferenceSubType::PagesApi),
+            ReferenceType::Entry(EntryReferenceSubType::AppPage),
+            ReferenceType::Entry(EntryReferenceSubType::AppRoute),
+            ReferenceType::Entry(EntryReferenceSubType::AppClientComponent),
+            ReferenceType::Entry(EntryReferenceSubType::ServerAction),
+            ReferenceType::Entry(EntryReferenceSubType::ServerComponent),
+            ReferenceType::Entry(EntryReferenceSubType::Middleware),
+            ReferenceType::Entry(EntryReferenceSubType::Instrumentation),
+            ReferenceType::Entry(EntryReferenceSubType::Runtime),
+            ReferenceType::Entry(EntryReferenceSubType::Undefined),
+            ReferenceType::Runtime,
+            ReferenceType::Manifest,
+            ReferenceType::Undefined,
+        ]
+        .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> ImportLayer {
+        ImportLayer::Named(name.to_string())
+    }
+
+    #[test]
+    fn import_context_is_empty() {
+        assert!(ImportContext::default().is_empty());
+        assert!(!ImportContext {
+            media: vec!["print".to_string()],
+            ..Default::default()
+        }
+        .is_empty());
+    }
+
+    #[test]
+    fn import_context_keeps_nesting_order() {
+        // a.css imported through `@import "x.css" screen` → `@import "a.css" print`
+        let via_x = ImportContext::default()
+            .with_attributes(ImportLayer::None, Some("screen".to_string()), None)
+            .with_attributes(ImportLayer::None, Some("print".to_string()), None);
+        // a.css imported through `@import "y.css" print` → `@import "a.css" screen`
+        let via_y = ImportContext::default()
+            .with_attributes(ImportLayer::None, Some("print".to_string()), None)
+            .with_attributes(ImportLayer::None, Some("screen".to_string()), None);
+
+        assert_eq!(via_x.media, vec!["screen", "print"]);
+        assert_eq!(via_y.media, vec!["print", "screen"]);
+        assert_ne!(via_x, via_y);
+    }
+
+    #[test]
+    fn import_context_eq() {
+        let context = |layer: &str, media: &str| {
+            ImportContext::default()
+                .with_attributes(named(layer), None, None)
+                .with_attributes(ImportLayer::None, Some(media.to_string()), None)
+        };
+
+        assert_eq!(context("base", "print"), context("base", "print"));
+        assert_ne!(context("base", "print"), context("base", "screen"));
+        assert_ne!(context("base", "print"), context("reset", "print"));
+        assert_ne!(context("base", "print"), ImportContext::default());
+    }
+
+    #[test]
+    fn import_context_keeps_repeated_layers() {
+        let context = ImportContext::default()
+            .with_attributes(named("a"), None, None)
+            .with_attributes(named("a"), None, None);
+        assert_eq!(context.layers, vec![named("a"), named("a")]);
+        assert_eq!(context.layer_blocks(), vec![named("a.a")]);
+    }
+
+    #[test]
+    fn import_context_layer_kinds() {
+        let root = ImportContext::default();
+
+        // `@import "a.css"` is not wrapped in a layer.
+        let none = root.with_attributes(ImportLayer::None, None, None);
+        assert_eq!(none, root);
+        assert!(none.layer_blocks().is_empty());
+
+        // `@import "a.css" layer` is wrapped in `@layer { ... }`.
+        let anonymous = root.with_attributes(ImportLayer::Anonymous, None, None);
+        assert_eq!(anonymous.layer_blocks(), vec![ImportLayer::Anonymous]);
+        assert!(ImportContext::layer_order([&anonymous]).is_empty());
+
+        // `@import "a.css" layer(a)` is wrapped in `@layer a { ... }`.
+        let named_layer = root.with_attributes(named("a"), None, None);
+        assert_eq!(named_layer.layer_blocks(), vec![named("a")]);
+        assert_eq!(ImportContext::layer_order([&named_layer]), vec!["a"]);
+
+        assert_ne!(anonymous, named_layer);
+    }
+
+    #[test]
+    fn import_context_layer_blocks() {
+        // An empty name stands for an anonymous layer.
+        let layer = |name: &str| {
+            if name.is_empty() {
+                ImportLayer::Anonymous
+            } else {
+                named(name)
+            }
+        };
+        let blocks = |layers: &[&str]| {
+            ImportContext {
+                layers: layers.iter().map(|name| layer(name)).collect(),
+                ..Default::default()
+            }
+            .layer_blocks()
+        };
+        let expected = |layers: &[&str]| layers.iter().map(|name| layer(name)).collect::<Vec<_>>();
+
+        assert!(blocks(&[]).is_empty());
+        assert_eq!(blocks(&["a", "b"]), expected(&["a.b"]));
+        assert_eq!(blocks(&["a", "", "b"]), expected(&["a", "", "b"]));
+        assert_eq!(blocks(&["", "a", "b"]), expected(&["", "a.b"]));
+        assert_eq!(blocks(&["a", ""]), expected(&["a", ""]));
+        assert_eq!(blocks(&["", ""]), expected(&["", ""]));
+    }
+
+    #[test]
+    fn import_context_wrappers() {
+        let context = ImportContext {
+            layers: vec![named("a"), named("b"), ImportLayer::Anonymous],
+            media: vec!["screen".to_string(), "(min-width: 100px)".to_string()],
+            supports: vec!["display: grid".to_string()],
+        };
+        let wrappers = context.wrappers().unwrap();
+
+        assert_eq!(
+            wrappers,
+            vec![
+                ImportWrapper::Layer(named("a.b")),
+                ImportWrapper::Layer(ImportLayer::Anonymous),
+                ImportWrapper::Supports("(display: grid)".to_string()),
+                ImportWrapper::Media("screen and (min-width: 100px)".to_string()),
+            ]
+        );
+        assert_eq!(
+            wrappers.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+            [
+                "@layer a.b",
+                "@layer",
+                "@supports (display: grid)",
+                "@media screen and (min-width: 100px)",
+            ]
+        );
+        assert!(ImportContext::default().wrappers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_context_layer_order() {
+        // style.css:
+        //   @import "reset.css" layer(base.reset);
+        //   @import "theme.css" layer(theme);
+        //   @import "grid.css" layer;
+        // theme.css:
+        //   @import "colors.css" layer(colors);
+        //   @import "more-reset.css" layer(base.reset);
+        let root = ImportContext::default();
+        let reset = root.with_attributes(named("base.reset"), None, None);
+        let theme = root.with_attributes(named("theme"), None, None);
+        let colors = theme.with_attributes(named("colors"), None, None);
+        let more_reset = theme.with_attributes(named("base.reset"), None, None);
+        let grid = root.with_attributes(ImportLayer::Anonymous, None, None);
+        let nested_in_grid = grid.with_attributes(named("inner"), None, None);
+
+        assert_eq!(
+            ImportContext::layer_order([
+                &reset,
+                &colors,
+                &more_reset,
+                &theme,
+                &grid,
+                &nested_in_grid,
+                &root
+            ]),
+            vec!["base.reset", "theme.colors", "theme.base.reset", "theme"]
+        );
+    }
+
+    #[test]
+    fn import_context_outer_attributes() {
+        // style.css: `@import "a.css" layer(x) screen`
+        // a.css: `@import "b.css" print supports(display: grid)`
+        // b.css: `@import "c.css" layer(y) screen`
+        let attributes = [
+            (named("x"), Some("screen"), None),
+            (ImportLayer::None, Some("print"), Some("display: grid")),
+            (named("y"), Some("screen"), None),
+        ]
+        .map(
+            |(layer, media, supports): (ImportLayer, Option<&str>, Option<&str>)| {
+                (
+                    layer,
+                    media.map(str::to_string),
+                    supports.map(str::to_string),
+                )
+            },
+        );
+
+        let outside_in = attributes.iter().fold(
+            ImportContext::default(),
+            |context, (layer, media, supports)| {
+                context.with_attributes(layer.clone(), media.clone(), supports.clone())
+            },
+        );
+        let inside_out = attributes.iter().rev().fold(
+            ImportContext::default(),
+            |context, (layer, media, supports)| {
+                context.with_attributes_outer(layer.clone(), media.clone(), supports.clone())
+            },
+        );
+
+        assert_eq!(outside_in.layers, vec![named("x"), named("y")]);
+        assert_eq!(outside_in.media, vec!["screen", "print"]);
+        assert_eq!(outside_in.supports, vec!["display: grid"]);
+        assert_eq!(inside_out, outside_in);
+    }
+
+    #[test]
+    fn import_context_skips_repeated_conditions() {
+        let context = ImportContext::default()
+            .with_attributes(ImportLayer::None, None, Some("(display: grid)".to_string()))
+            .with_attributes(ImportLayer::None, None, Some("(display: grid)".to_string()));
+        assert_eq!(context.supports, vec!["(display: grid)"]);
+    }
+
+    #[test]
+    fn from_category_str() {
+        for ty in ReferenceType::well_known() {
+            let parsed = ReferenceType::from_category_str(&ty.to_string()).unwrap();
+            assert_eq!(parsed.category(), ty.category(), "{ty}");
+            assert!(parsed.includes(&ty), "{ty}");
+            assert_eq!(
+                parsed.to_string().split(" (").next(),
+                ty.to_string().split(" (").next()
+            );
+        }
+
+        // The sub type is lost.
+        assert_eq!(
+            ReferenceType::from_category_str("EcmaScript Modules (part)"),
+            Some(ReferenceType::EcmaScriptModules(
+                EcmaScriptModulesReferenceSubType::Undefined
+            ))
+        );
+        assert_eq!(
+            ReferenceType::from_category_str("css"),
+            Some(ReferenceType::Css(CssReferenceSubType::Undefined))
+        );
+        assert_eq!(ReferenceType::from_category_str("internal"), None);
+        assert_eq!(ReferenceType::from_category_str("CSS"), None);
+        assert_eq!(ReferenceType::from_category_str(""), None);
+
+        assert_eq!(
+            ReferenceType::from_category_str("custom(7)"),
+            Some(ReferenceType::Custom(7))
+        );
+        register_custom_reference_name(202, "from str").unwrap();
+        let custom = ReferenceType::Custom(202);
+        assert_eq!(
+            ReferenceType::from_category_str(&custom.to_string()),
+            Some(custom)
+        );
+        assert_eq!(ReferenceType::from_category_str("custom(unknown)"), None);
+    }
+
+    #[test]
+    fn custom_display() {
+        assert_eq!(ReferenceType::Custom(7).to_string(), "custom(7)");
+    }
+
+    #[test]
+    fn sub_type_display() {
+        assert_eq!(
+            ReferenceType::Css(CssReferenceSubType::Compose(None)).to_string(),
+            "css (compose)"
+        );
+        assert_eq!(
+            ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl).to_string(),
+            "url (new URL)"
+        );
+        assert_eq!(
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined).to_string(),
+            "commonjs (undefined)"
+        );
+        assert_eq!(ReferenceType::Runtime.to_string(), "runtime");
+        assert_eq!(ReferenceType::Manifest.to_string(), "manifest");
+    }
+
+    #[test]
+    fn custom_display_with_name() {
+        let ty = ReferenceType::custom_with_name(200, "postcss-modules").unwrap();
+        assert_eq!(ty.to_string(), "custom(postcss-modules)");
+    }
+
+    #[test]
+    fn custom_name_is_fill_once() {
+        register_custom_reference_name(201, "a").unwrap();
+        register_custom_reference_name(201, "a").unwrap();
+        assert!(register_custom_reference_name(201, "b").is_err());
+        assert_eq!(ReferenceType::Custom(201).to_string(), "custom(a)");
+    }
+
+    #[test]
+    fn custom_includes_custom() {
+        assert!(ReferenceType::Custom(1).includes(&ReferenceType::Custom(1)));
+        assert!(!ReferenceType::Custom(1).includes(&ReferenceType::Custom(2)));
+    }
+
+    #[test]
+    fn custom_includes_other() {
+        assert!(!ReferenceType::Custom(1).includes(&ReferenceType::Runtime));
+        assert!(!ReferenceType::Runtime.includes(&ReferenceType::Custom(1)));
+        assert!(ReferenceType::Undefined.includes(&ReferenceType::Custom(1)));
+    }
+
+    #[test]
+    fn undefined_sub_type_includes_category() {
+        let undefined = [
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined),
+            ReferenceType::Css(CssReferenceSubType::Undefined),
+            ReferenceType::Url(UrlReferenceSubType::Undefined),
+            ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+            ReferenceType::Json(JsonReferenceSubType::Undefined),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Undefined),
+            ReferenceType::Entry(EntryReferenceSubType::Undefined),
+        ];
+        let types = [
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Custom(1)),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+            ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+            ReferenceType::Url(UrlReferenceSubType::CssUrl),
+            ReferenceType::TypeScript(TypeScriptReferenceSubType::Custom(1)),
+            ReferenceType::Json(JsonReferenceSubType::Require),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Sync),
+            ReferenceType::Entry(EntryReferenceSubType::Page),
+            ReferenceType::Runtime,
+            ReferenceType::Custom(1),
+        ];
+        for condition in &undefined {
+            for ty in &types {
+                assert_eq!(
+                    condition.includes(ty),
+                    condition.category() == ty.category(),
+                    "{condition} includes {ty}"
+                );
+                // Only `Undefined` sub types include other sub types.
+                assert!(!ty.includes(condition), "{ty} includes {condition}");
+            }
+        }
+    }
+
+    #[test]
+    fn category_predicates() {
+        let predicates: [fn(&ReferenceType) -> bool; 8] = [
+            ReferenceType::is_commonjs,
+            ReferenceType::is_ecmascript,
+            ReferenceType::is_css,
+            ReferenceType::is_url,
+            ReferenceType::is_typescript,
+            ReferenceType::is_json,
+            ReferenceType::is_wasm,
+            ReferenceType::is_entry,
+        ];
+        let types = [
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+            ReferenceType::Css(CssReferenceSubType::Compose(None)),
+            ReferenceType::Url(UrlReferenceSubType::CssUrl),
+            ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+            ReferenceType::Json(JsonReferenceSubType::Require),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async),
+            ReferenceType::Entry(EntryReferenceSubType::Page),
+        ];
+        for (i, ty) in types.iter().enumerate() {
+            for (j, predicate) in predicates.iter().enumerate() {
+                assert_eq!(predicate(ty), i == j, "{ty}");
+            }
+        }
+        for ty in [
+            ReferenceType::Runtime,
+            ReferenceType::Manifest,
+            ReferenceType::Custom(1),
+            ReferenceType::Undefined,
+        ] {
+            assert!(predicates.iter().all(|predicate| !predicate(&ty)), "{ty}");
+        }
+    }
+
+    #[test]
+    fn category() {
+        let cases = [
+            (
+                ReferenceType::CommonJs(CommonJsReferenceSubType::Custom(1)),
+                ReferenceCategory::CommonJs,
+            ),
+            (
+                ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport),
+                ReferenceCategory::EcmaScriptModules,
+            ),
+            (
+                ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+                ReferenceCategory::Css,
+            ),
+            (
+                ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl),
+                ReferenceCategory::Url,
+            ),
+            (
+                ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+                ReferenceCategory::TypeScript,
+            ),
+            (
+                ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes),
+                ReferenceCategory::Json,
+            ),
+            (
+                ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Url),
+                ReferenceCategory::WebAssembly,
+            ),
+            (
+                ReferenceType::Entry(EntryReferenceSubType::Middleware),
+                ReferenceCategory::Entry,
+            ),
+            (ReferenceType::Runtime, ReferenceCategory::Runtime),
+            (ReferenceType::Manifest, ReferenceCategory::Manifest),
+            (ReferenceType::Custom(3), ReferenceCategory::Custom),
+            (ReferenceType::Undefined, ReferenceCategory::Undefined),
+        ];
+        for (ty, category) in cases {
+            assert_eq!(ty.category(), category, "{ty}");
+        }
+        // The sub type doesn't affect the category.
+        assert_eq!(
+            ReferenceType::Css(CssReferenceSubType::Compose(None)).category(),
+            ReferenceType::Css(CssReferenceSubType::Undefined).category()
+        );
+    }
+
+    #[test]
+    fn parse_import_attributes() {
+        let attributes =
+            ImportAttributes::parse(r#"@import "x.css" layer(a) supports(display:grid) screen;"#)
+                .unwrap();
+        assert_eq!(attributes.layer, named("a"));
+        assert_eq!(attributes.supports.as_deref(), Some("display:grid"));
+        assert_eq!(attributes.media.as_deref(), Some("screen"));
+
+        let attributes = ImportAttributes::parse("url(x.css)").unwrap();
+        assert_eq!(attributes.layer, ImportLayer::None);
+        assert_eq!(attributes.supports, None);
+        assert_eq!(attributes.media, None);
+    }
+
+    #[test]
+    fn import_attributes_eq() {
+        let a =
+            ImportAttributes::parse(r#"@import "a.css" layer(a) supports(display: grid) print;"#)
+                .unwrap();
+        let b =
+            ImportAttributes::parse("url(b.css) layer(a) supports(display: grid) print").unwrap();
+        let other = ImportAttributes::parse(r#"@import "a.css" layer(a) screen"#).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, other);
+
+        let set = [a, b, other]
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn parse_import_attributes_anonymous_layer() {
+        let attributes = ImportAttributes::parse(r#"@import "x.css" layer print"#).unwrap();
+        assert_eq!(attributes.layer, ImportLayer::Anonymous);
+        assert_eq!(attributes.media.as_deref(), Some("print"));
+
+        let attributes = ImportAttributes::parse(r#"@import "x.css" LAYER;"#).unwrap();
+        assert_eq!(attributes.layer, ImportLayer::Anonymous);
+        assert_eq!(attributes.media, None);
+
+        // Not the `layer` keyword.
+        let attributes = ImportAttributes::parse(r#"@import "x.css" layered"#).unwrap();
+        assert_eq!(attributes.layer, ImportLayer::None);
+        assert_eq!(attributes.media.as_deref(), Some("layered"));
+    }
+
+    #[test]
+    fn parse_import_attributes_quoted() {
+        let attributes = ImportAttributes::parse(
+            r#"@import 'a "b" (c).css' supports(content: ")") (min-width: 100px), "print""#,
+        )
+        .unwrap();
+        assert_eq!(attributes.layer, ImportLayer::None);
+        assert_eq!(attributes.supports.as_deref(), Some(r#"content: ")""#));
+        assert_eq!(
+            attributes.media.as_deref(),
+            Some(r#"(min-width: 100px), "print""#)
+        );
+
+        assert!(ImportAttributes::parse(r#"@import "x.css"#).is_err());
+    }
+
+    #[test]
+    fn parse_import_attributes_nested_supports() {
+        let attributes = ImportAttributes::parse(
+            r#"@import url("x.css") layer(base.reset) supports((display: grid) and (not (display: inline-grid))) screen and (orientation: landscape)"#,
+        )
+        .unwrap();
+        assert_eq!(attributes.layer, named("base.reset"));
+        assert_eq!(
+            attributes.supports.as_deref(),
+            Some("(display: grid) and (not (display: inline-grid))")
+        );
+        assert_eq!(
+            attributes.media.as_deref(),
+            Some("screen and (orientation: landscape)")
+        );
+
+        assert!(ImportAttributes::parse(r#"@import "x.css" supports((display: grid)"#).is_err());
+        assert!(ImportAttributes::parse("screen").is_err());
+    }
+
+    #[test]
+    fn import_context_iter() {
+        let context = ImportContext {
+            layers: vec![named("a"), ImportLayer::Anonymous],
+            supports: vec!["display: grid".to_string()],
+            media: vec!["screen".to_string(), "(min-width: 100px)".to_string()],
+        };
+        assert_eq!(context.len(), 5);
+        assert_eq!(
+            context.iter().collect::<Vec<_>>(),
+            [
+                (ImportDimension::Layer, "a"),
+                (ImportDimension::Layer, ""),
+                (ImportDimension::Supports, "display: grid"),
+                (ImportDimension::Media, "screen"),
+                (ImportDimension::Media, "(min-width: 100px)"),
+            ]
+        );
+
+        let empty = ImportContext::default();
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.iter().count(), 0);
+    }
+
+    fn media_query(media: &[&str]) -> Option<String> {
+        ImportContext {
+            media: media.iter().map(|media| media.to_string()).collect(),
+            ..Default::default()
+        }
+        .media_query()
+        .unwrap()
+    }
+
+    #[test]
+    fn media_query_single() {
+        assert_eq!(media_query(&[]), None);
+        assert_eq!(media_query(&["print"]).as_deref(), Some("print"));
+        assert_eq!(
+            media_query(&["screen and (min-width: 100px)"]).as_deref(),
+            Some("screen and (min-width: 100px)")
+        );
+        assert_eq!(
+            media_query(&["(color) or (hover)"]).as_deref(),
+            Some("(color) or (hover)")
+        );
+    }
+
+    #[test]
+    fn media_query_nested() {
+        assert_eq!(
+            media_query(&["screen", "(min-width: 100px)"]).as_deref(),
+            Some("screen and (min-width: 100px)")
+        );
+        assert_eq!(
+            media_query(&["(min-width: 100px)", "only screen and (color)", "all"]).as_deref(),
+            Some("screen and (min-width: 100px) and (color)")
+        );
+        assert_eq!(
+            media_query(&["(color) or (hover)", "(min-width: 100px)"]).as_deref(),
+            Some("((color) or (hover)) and (min-width: 100px)")
+        );
+        assert_eq!(
+            media_query(&["screen", "print"]).as_deref(),
+            Some("not all")
+        );
+        assert!(ImportContext {
+            media: vec!["screen".to_string(), "not print".to_string()],
+            ..Default::default()
+        }
+        .media_query()
+        .is_err());
+    }
+
+    #[test]
+    fn media_query_comma_lists() {
+        assert_eq!(
+            media_query(&["screen, print", "(min-width: 100px)"]).as_deref(),
+            Some("screen and (min-width: 100px), print and (min-width: 100px)")
+        );
+        assert_eq!(
+            media_query(&["(color), (hover)", "screen, print"]).as_deref(),
+            Some("screen and (color), print and (color), screen and (hover), print and (hover)")
+        );
+        assert_eq!(
+            media_query(&["screen, print", "print"]).as_deref(),
+            Some("print")
+        );
+    }
+
+    fn supports_query(supports: &[&str]) -> Option<String> {
+        ImportContext {
+            supports: supports
+                .iter()
+                .map(|supports| supports.to_string())
+                .collect(),
+            ..Default::default()
+        }
+        .supports_query()
+    }
+
+    #[test]
+    fn supports_query_single() {
+        assert_eq!(supports_query(&[]), None);
+        assert_eq!(
+            supports_query(&["display: grid"]).as_deref(),
+            Some("(display: grid)")
+        );
+    }
+
+    #[test]
+    fn supports_query_multiple() {
+        assert_eq!(
+            supports_query(&["display: grid", "not (display: inline-grid)"]).as_deref(),
+            Some("(display: grid) and (not (display: inline-grid))")
+        );
+    }
+
+    #[test]
+    fn supports_query_parenthesized() {
+        assert_eq!(
+            supports_query(&["(display: grid)", "(display: flex) or (display: block)"]).as_deref(),
+            Some("(display: grid) and ((display: flex) or (display: block))")
+        );
+    }
+
+    #[test]
+    fn supports_query_selector() {
+        assert_eq!(
+            supports_query(&["selector(a > b)"]).as_deref(),
+            Some("selector(a > b)")
+        );
+        assert_eq!(
+            supports_query(&["display: grid", "selector(:has(> img))"]).as_deref(),
+            Some("(display: grid) and selector(:has(> img))")
+        );
+        // Only a single function is kept as is.
+        assert_eq!(
+            supports_query(&["selector(a) or (display: grid)"]).as_deref(),
+            Some("(selector(a) or (display: grid))")
+        );
+    }
+
+    #[test]
+    fn manifest_includes() {
+        let manifest = ReferenceType::Manifest;
+
+        assert!(manifest.is_internal());
+        assert!(manifest.includes(&manifest));
+        assert!(ReferenceType::Undefined.includes(&manifest));
+        assert!(!manifest.includes(&ReferenceType::Runtime));
+        assert!(!ReferenceType::Runtime.includes(&manifest));
+        assert!(!ReferenceType::Entry(EntryReferenceSubType::Undefined).includes(&manifest));
+    }
+
+    #[test]
+    fn url_worker_includes() {
+        let worker = ReferenceType::Url(UrlReferenceSubType::Worker);
+        let new_url = ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl);
+
+        assert!(worker.includes(&worker));
+        assert!(!worker.includes(&new_url));
+        assert!(!new_url.includes(&worker));
+        assert!(ReferenceType::Url(UrlReferenceSubType::Undefined).includes(&worker));
+        assert!(!worker.is_internal());
+        assert_eq!(worker.to_string(), "url (worker)");
+    }
+
+    #[test]
+    fn url_resource_hint_includes() {
+        let preload = ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Preload));
+        let prefetch =
+            ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Prefetch));
+
+        assert!(preload.includes(&preload));
+        assert!(prefetch.includes(&prefetch));
+        assert!(!preload.includes(&prefetch));
+        assert!(!prefetch.includes(&preload));
+        assert!(ReferenceType::Url(UrlReferenceSubType::Undefined).includes(&preload));
+        assert!(ReferenceType::Url(UrlReferenceSubType::Undefined).includes(&prefetch));
+        assert!(!preload.includes(&ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl)));
+        assert!(!preload.is_async());
+        assert_eq!(preload.to_string(), "url (preload)");
+        assert_eq!(prefetch.to_string(), "url (prefetch)");
+    }
+
+    #[test]
+    fn esm_re_export_includes() {
+        let esm = ReferenceType::EcmaScriptModules;
+        let export_all = esm(EcmaScriptModulesReferenceSubType::ExportAll);
+        let re_export = esm(EcmaScriptModulesReferenceSubType::ReExport);
+        let import = esm(EcmaScriptModulesReferenceSubType::Import);
+
+        assert!(export_all.includes(&export_all));
+        assert!(re_export.includes(&re_export));
+        assert!(!export_all.includes(&re_export));
+        assert!(!re_export.includes(&export_all));
+        assert!(!import.includes(&export_all));
+        assert!(!re_export.includes(&import));
+        assert!(esm(EcmaScriptModulesReferenceSubType::Undefined).includes(&export_all));
+        assert!(esm(EcmaScriptModulesReferenceSubType::Undefined).includes(&re_export));
+        assert!(!esm(EcmaScriptModulesReferenceSubType::Custom(1)).includes(&re_export));
+        assert_eq!(export_all.to_string(), "EcmaScript Modules (export all)");
+        assert_eq!(re_export.to_string(), "EcmaScript Modules (re-export)");
+    }
+
+    #[test]
+    fn at_import_layer_only_includes() {
+        let any = ReferenceType::Css(CssReferenceSubType::AtImport(None));
+        let layer = |name: &str| {
+            ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(vec![
+                ImportLayer::Named(name.to_string()),
+            ]))
+        };
+        let anonymous = ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(vec![
+            ImportLayer::Anonymous,
+        ]));
+
+        assert!(any.includes(&layer("a")));
+        assert!(layer("a").includes(&any));
+        assert!(layer("a").includes(&layer("a")));
+        assert!(!layer("a").includes(&layer("b")));
+        assert!(!layer("a").includes(&anonymous));
+        assert!(ReferenceType::Css(CssReferenceSubType::Undefined).includes(&layer("a")));
+        assert!(!layer("a").includes(&ReferenceType::Css(CssReferenceSubType::Compose(None))));
+        assert_eq!(layer("a").to_string(), "css (at-import layer)");
+    }
+
+    #[test]
+    fn entry_server_includes() {
+        let action = ReferenceType::Entry(EntryReferenceSubType::ServerAction);
+        let component = ReferenceType::Entry(EntryReferenceSubType::ServerComponent);
+        let client = ReferenceType::Entry(EntryReferenceSubType::AppClientComponent);
+
+        assert!(action.includes(&action));
+        assert!(component.includes(&component));
+        assert!(!action.includes(&component));
+        assert!(!component.includes(&action));
+        assert!(!component.includes(&client));
+        assert!(!client.includes(&component));
+        assert!(ReferenceType::Entry(EntryReferenceSubType::Undefined).includes(&action));
+        assert!(ReferenceType::Entry(EntryReferenceSubType::Undefined).includes(&component));
+        assert!(ReferenceType::Undefined.includes(&component));
+        assert!(!action.includes(&ReferenceType::Entry(EntryReferenceSubType::Undefined)));
+        assert_eq!(action.to_string(), "entry (server action)");
+        assert_eq!(component.to_string(), "entry (server component)");
+    }
+
+    #[test]
+    fn entry_named_includes() {
+        let named =
+            |name: &str| ReferenceType::Entry(EntryReferenceSubType::Named(name.to_string()));
+
+        assert!(named("edge function").includes(&named("edge function")));
+        assert_eq!(named("edge function"), named("edge function"));
+        assert!(!named("edge function").includes(&named("server function")));
+        // Named entries are distinct from the hardcoded variants, even with
+        // the same display name.
+        let page = ReferenceType::Entry(EntryReferenceSubType::Page);
+        assert_eq!(named("page").to_string(), page.to_string());
+        assert_ne!(named("page"), page);
+        assert!(!named("page").includes(&page));
+        assert!(!page.includes(&named("page")));
+        assert!(ReferenceType::Entry(EntryReferenceSubType::Undefined).includes(&named("page")));
+        assert_eq!(named("edge function").to_string(), "entry (edge function)");
+        assert_eq!(named("edge function").sub_type_name(), Some("named"));
+    }
+
+    #[test]
+    fn esm_import_meta_includes() {
+        let esm = ReferenceType::EcmaScriptModules;
+        let url = esm(EcmaScriptModulesReferenceSubType::ImportMeta(
+            ImportMetaProperty::Url,
+        ));
+        let resolve = esm(EcmaScriptModulesReferenceSubType::ImportMeta(
+            ImportMetaProperty::Resolve,
+        ));
+        let import = esm(EcmaScriptModulesReferenceSubType::Import);
+
+        assert!(url.includes(&url));
+        assert!(resolve.includes(&resolve));
+        assert!(!url.includes(&resolve));
+        assert!(!resolve.includes(&url));
+        assert!(!import.includes(&url));
+        assert!(!resolve.includes(&import));
+        assert!(esm(EcmaScriptModulesReferenceSubType::Undefined).includes(&url));
+        assert!(esm(EcmaScriptModulesReferenceSubType::Undefined).includes(&resolve));
+        // `import.meta.resolve` is the ESM counterpart of `require.resolve`, but
+        // the categories differ.
+        assert!(!resolve.includes(&ReferenceType::CommonJs(
+            CommonJsReferenceSubType::RequireResolve
+        )));
+        assert!(!url.is_async());
+        assert!(!resolve.is_async());
+        assert_eq!(url.to_string(), "EcmaScript Modules (import.meta.url)");
+        assert_eq!(
+            resolve.to_string(),
+            "EcmaScript Modules (import.meta.resolve)"
+        );
+    }
+
+    #[test]
+    fn commonjs_require_includes() {
+        let require = ReferenceType::CommonJs(CommonJsReferenceSubType::Require);
+        let require_resolve = ReferenceType::CommonJs(CommonJsReferenceSubType::RequireResolve);
+        let undefined = ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined);
+
+        assert!(require.includes(&require));
+        assert!(require_resolve.includes(&require_resolve));
+        assert!(!require.includes(&require_resolve));
+        assert!(!require_resolve.includes(&require));
+        assert!(undefined.includes(&require));
+        assert!(undefined.includes(&require_resolve));
+        assert!(!require.includes(&undefined));
+        assert!(!require.includes(&ReferenceType::Json(JsonReferenceSubType::Require)));
+        assert_eq!(require.to_string(), "commonjs (require)");
+        assert_eq!(require_resolve.to_string(), "commonjs (require.resolve)");
+    }
+
+    #[test]
+    fn json_includes() {
+        let import = ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes);
+        let require = ReferenceType::Json(JsonReferenceSubType::Require);
+
+        assert!(import.includes(&import));
+        assert!(!import.includes(&require));
+        assert!(!require.includes(&import));
+        assert!(ReferenceType::Json(JsonReferenceSubType::Undefined).includes(&import));
+        assert!(ReferenceType::Json(JsonReferenceSubType::Undefined).includes(&require));
+        // JSON references are neither CommonJS nor ESM references.
+        assert!(!ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined).includes(&require));
+        assert!(
+            !ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined)
+                .includes(&import)
+        );
+        assert!(
+            !ReferenceType::Json(JsonReferenceSubType::Undefined).includes(
+                &ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined)
+            )
+        );
+        assert!(ReferenceType::Undefined.includes(&import));
+        assert!(!import.is_internal());
+        assert_eq!(import.to_string(), "json (import with attributes)");
+    }
+
+    #[test]
+    fn wasm_includes() {
+        let wasm = ReferenceType::WebAssembly;
+        let sync = wasm(WebAssemblyReferenceSubType::Sync);
+        let async_ = wasm(WebAssemblyReferenceSubType::Async);
+        let url = wasm(WebAssemblyReferenceSubType::Url);
+
+        assert!(sync.includes(&sync));
+        assert!(!sync.includes(&async_));
+        assert!(!async_.includes(&sync));
+        assert!(!async_.includes(&url));
+        assert!(wasm(WebAssemblyReferenceSubType::Undefined).includes(&sync));
+        assert!(wasm(WebAssemblyReferenceSubType::Undefined).includes(&url));
+        assert!(!ReferenceType::Url(UrlReferenceSubType::Undefined).includes(&url));
+        assert!(!url.is_internal());
+        assert_eq!(async_.to_string(), "wasm (async)");
+        assert_eq!(url.to_string(), "wasm (url)");
+    }
+
+    #[test]
+    fn wasm_from_query() {
+        use WebAssemblyReferenceSubType::{Module, Url};
+
+        assert_eq!(WebAssemblyReferenceSubType::from_query("?url"), Some(Url));
+        assert_eq!(
+            WebAssemblyReferenceSubType::from_query("?module"),
+            Some(Module)
+        );
+        assert_eq!(
+            WebAssemblyReferenceSubType::from_query("?v=1&url"),
+            Some(Url)
+        );
+        assert_eq!(
+            WebAssemblyReferenceSubType::from_query("?url&url"),
+            Some(Url)
+        );
+        assert_eq!(WebAssemblyReferenceSubType::from_query("?url&module"), None);
+        assert_eq!(WebAssemblyReferenceSubType::from_query("?url=1"), None);
+        assert_eq!(WebAssemblyReferenceSubType::from_query("?modules"), None);
+        assert_eq!(WebAssemblyReferenceSubType::from_query(""), None);
+    }
+
+    /// Returns true if `ty` is expected in [ReferenceType::well_known]. The
+    /// match is exhaustive, so new variants need to be added here, and to the
+    /// count in the `well_known` test if they are well known.
+    fn is_well_known(ty: &ReferenceType) -> bool {
+        match ty {
+            ReferenceType::CommonJs(sub_type) => match sub_type {
+                CommonJsReferenceSubType::Custom(_) => false,
+                CommonJsReferenceSubType::Require
+                | CommonJsReferenceSubType::RequireResolve
+                | CommonJsReferenceSubType::Undefined => true,
+            },
+            ReferenceType::EcmaScriptModules(sub_type) => match sub_type {
+                EcmaScriptModulesReferenceSubType::ImportPart(_)
+                | EcmaScriptModulesReferenceSubType::Custom(_) => false,
+                EcmaScriptModulesReferenceSubType::Import
+                | EcmaScriptModulesReferenceSubType::DynamicImport
+                | EcmaScriptModulesReferenceSubType::ExportAll
+                | EcmaScriptModulesReferenceSubType::ReExport
+                | EcmaScriptModulesReferenceSubType::ImportMeta(_)
+                | EcmaScriptModulesReferenceSubType::Undefined => true,
+            },
+            ReferenceType::Css(sub_type) => match sub_type {
+                CssReferenceSubType::AtImport(context)
+                | CssReferenceSubType::AtImportUrl(context) => context.is_none(),
+                CssReferenceSubType::AtImportLayerOnly(_) => false,
+                CssReferenceSubType::Use(alias) | CssReferenceSubType::Forward(alias) => {
+                    alias.is_none()
+                }
+                CssReferenceSubType::Compose(target) => target.is_none(),
+                CssReferenceSubType::Custom(_) => false,
+                CssReferenceSubType::Internal | CssReferenceSubType::Undefined => true,
+            },
+            ReferenceType::Url(sub_type) => match sub_type {
+                UrlReferenceSubType::Custom(_) => false,
+                UrlReferenceSubType::EcmaScriptNewUrl
+                | UrlReferenceSubType::CssUrl
+                | UrlReferenceSubType::Worker
+                | UrlReferenceSubType::ResourceHint(_)
+                | UrlReferenceSubType::Undefined => true,
+            },
+            ReferenceType::TypeScript(sub_type) => match sub_type {
+                TypeScriptReferenceSubType::Custom(_) => false,
+                TypeScriptReferenceSubType::Undefined => true,
+            },
+            ReferenceType::Json(sub_type) => match sub_type {
+                JsonReferenceSubType::ImportPartWithAttributes(_)
+                | JsonReferenceSubType::Custom(_) => false,
+                JsonReferenceSubType::ImportWithAttributes
+                | JsonReferenceSubType::Require
+                | JsonReferenceSubType::Undefined => true,
+            },
+            ReferenceType::WebAssembly(sub_type) => match sub_type {
+                WebAssemblyReferenceSubType::Custom(_) => false,
+                WebAssemblyReferenceSubType::Sync
+                | WebAssemblyReferenceSubType::Async
+                | WebAssemblyReferenceSubType::Url
+                | WebAssemblyReferenceSubType::Module
+                | WebAssemblyReferenceSubType::Undefined => true,
+            },
+            ReferenceType::Entry(sub_type) => match sub_type {
+                EntryReferenceSubType::Named(_) | EntryReferenceSubType::Custom(_) => false,
+                EntryReferenceSubType::Web
+                | EntryReferenceSubType::Page
+                | EntryReferenceSubType::PagesApi
+                | EntryReferenceSubType::AppPage
+                | EntryReferenceSubType::AppRoute
+                | EntryReferenceSubType::AppClientComponent
+                | EntryReferenceSubType::ServerAction
+                | EntryReferenceSubType::ServerComponent
+                | EntryReferenceSubType::Middleware
+                | EntryReferenceSubType::Instrumentation
+                | EntryReferenceSubType::Runtime
+                | EntryReferenceSubType::Undefined => true,
+            },
+            ReferenceType::Internal(_) | ReferenceType::Custom(_) => false,
+            ReferenceType::Runtime | ReferenceType::Manifest | ReferenceType::Undefined => true,
+        }
+    }
+
+    #[test]
+    fn well_known() {
+        let types: Vec<_> = ReferenceType::well_known().collect();
+        // The number of well known variants in `is_well_known`.
+        assert_eq!(types.len(), 47);
+        for (i, ty) in types.iter().enumerate() {
+            assert!(is_well_known(ty), "{ty}");
+            assert!(!types[..i].contains(ty), "{ty} is listed twice");
+        }
+    }
+
+    #[test]
+    fn is_css_related() {
+        let url_sub_types = [
+            (UrlReferenceSubType::EcmaScriptNewUrl, false),
+            (UrlReferenceSubType::CssUrl, true),
+            (UrlReferenceSubType::Worker, false),
+            (
+                UrlReferenceSubType::ResourceHint(ResourceHint::Preload),
+                false,
+            ),
+            (UrlReferenceSubType::Custom(1), false),
+            (UrlReferenceSubType::Undefined, false),
+        ];
+        for (sub_type, expected) in url_sub_types {
+            let ty = ReferenceType::Url(sub_type);
+            assert_eq!(ty.is_css_related(), expected, "{ty}");
+            assert!(!ty.is_css(), "{ty}");
+        }
+
+        for sub_type in [
+            CssReferenceSubType::AtImport(None),
+            CssReferenceSubType::Use(None),
+            CssReferenceSubType::Forward(None),
+            CssReferenceSubType::Compose(None),
+            CssReferenceSubType::Internal,
+            CssReferenceSubType::Custom(1),
+            CssReferenceSubType::Undefined,
+        ] {
+            assert!(ReferenceType::Css(sub_type).is_css_related());
+        }
+        assert!(
+            !ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import)
+                .is_css_related()
+        );
+        assert!(!ReferenceType::Undefined.is_css_related());
+    }
+
+    #[test]
+    fn specificity() {
+        let at_import = ReferenceType::Css(CssReferenceSubType::AtImport(None));
+        let layer = ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(vec![
+            ImportLayer::Named("a".to_string()),
+        ]));
+        let css = ReferenceType::Css(CssReferenceSubType::Undefined);
+
+        assert!(layer.specificity() > at_import.specificity());
+        assert!(at_import.specificity() > css.specificity());
+        assert!(css.specificity() > ReferenceType::Undefined.specificity());
+        assert_eq!(
+            ReferenceType::Css(CssReferenceSubType::Custom(1)).specificity(),
+            at_import.specificity()
+        );
+        assert_eq!(
+            ReferenceType::Custom(1).specificity(),
+            ReferenceType::Runtime.specificity()
+        );
+        assert!(ReferenceType::Custom(1).specificity() > css.specificity());
+
+        // Conditions with a specificity of 1 include their whole category.
+        for ty in ReferenceType::well_known() {
+            if ty.specificity() == 1 {
+                assert!(ReferenceType::well_known()
+                    .filter(|other| other.category() == ty.category())
+                    .all(|other| ty.includes(&other)));
+            }
+        }
+    }
+
+    #[test]
+    fn is_async() {
+        let async_types = [
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport),
+            ReferenceType::Url(UrlReferenceSubType::Worker),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async),
+        ];
+        for ty in &async_types {
+            assert!(ty.is_async(), "{ty}");
+        }
+
+        let sync_types = [
+            ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ExportAll),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ReExport),
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined),
+            ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+            ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl),
+            ReferenceType::Url(UrlReferenceSubType::CssUrl),
+            ReferenceType::Url(UrlReferenceSubType::Undefined),
+            ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+            ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Sync),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Url),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Module),
+            ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Undefined),
+            ReferenceType::Entry(EntryReferenceSubType::Page),
+            ReferenceType::Runtime,
+            ReferenceType::Custom(1),
+            ReferenceType::Undefined,
+        ];
+        for ty in &sync_types {
+            assert!(!ty.is_async(), "{ty}");
+        }
+    }
 }
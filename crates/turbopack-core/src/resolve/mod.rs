@@ -35,7 +35,7 @@ use crate::{
     output::{OutputAsset, OutputAssets},
     package_json::{read_package_json, PackageJsonIssue},
     raw_module::RawModule,
-    reference_type::ReferenceType,
+    reference_type::{EcmaScriptModulesReferenceSubType, ReferenceType},
     resolve::{
         pattern::{read_matches, PatternMatch},
         plugin::ResolvePlugin,
@@ -205,6 +205,8 @@ impl ModuleResolveResult {
                 .filter(|source| !set.contains(source))
                 .copied(),
         );
+        self.exports_field_traces
+            .extend(other.exports_field_traces.iter().cloned());
     }
 
     pub fn is_unresolveable_ref(&self) -> bool {
@@ -450,11 +452,30 @@ impl RequestKey {
     }
 }
 
+/// Explains how a subpath was matched in the exports or imports field of a
+/// package. Only recorded when
+/// [ResolveOptions::exports_field_trace](options::ResolveOptions::exports_field_trace)
+/// is set.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct ExportsFieldTrace {
+    /// The path to the package.json which contains the field.
+    pub package_json_path: Vc<FileSystemPath>,
+    /// The subpath that was looked up, e.g. `.` or `#internal`.
+    pub subpath: String,
+    /// The conditions which were entered to reach the target, in order.
+    pub conditions: Vec<String>,
+    /// The chosen target, or `None` when the subpath is blocked with a
+    /// `null` target or no target matched.
+    pub target: Option<String>,
+}
+
 #[turbo_tasks::value(shared)]
 #[derive(Clone, Debug)]
 pub struct ResolveResult {
     pub primary: IndexMap<RequestKey, ResolveResultItem>,
     pub affecting_sources: Vec<Vc<Box<dyn Source>>>,
+    pub exports_field_traces: Vec<ExportsFieldTrace>,
 }
 
 impl Default for ResolveResult {
@@ -516,6 +537,7 @@ impl ResolveResult {
         ResolveResult {
             primary: IndexMap::new(),
             affecting_sources: Vec::new(),
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -525,6 +547,7 @@ impl ResolveResult {
         ResolveResult {
             primary: IndexMap::new(),
             affecting_sources,
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -536,6 +559,7 @@ impl ResolveResult {
         ResolveResult {
             primary: indexmap! { request_key => result },
             affecting_sources: Vec::new(),
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -547,6 +571,7 @@ impl ResolveResult {
         ResolveResult {
             primary: indexmap! { request_key => result },
             affecting_sources,
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -558,6 +583,7 @@ impl ResolveResult {
         ResolveResult {
             primary: indexmap! { request_key => ResolveResultItem::Source(source) },
             affecting_sources: Vec::new(),
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -569,6 +595,7 @@ impl ResolveResult {
         ResolveResult {
             primary: indexmap! { request_key => ResolveResultItem::Source(source) },
             affecting_sources,
+            exports_field_traces: Vec::new(),
         }
     }
 
@@ -587,6 +614,7 @@ impl ResolveResult {
         ResolveResult {
             primary: self.primary.clone(),
             affecting_sources,
+            exports_field_traces: self.exports_field_traces.clone(),
         }
     }
 
@@ -608,6 +636,8 @@ impl ResolveResult {
                 .filter(|source| !set.contains(source))
                 .copied(),
         );
+        self.exports_field_traces
+            .extend(other.exports_field_traces.iter().cloned());
     }
 
     pub fn is_unresolveable_ref(&self) -> bool {
@@ -648,6 +678,7 @@ impl ResolveResult {
                 .map(affecting_source_fn)
                 .try_join()
                 .await?,
+            exports_field_traces: self.exports_field_traces.clone(),
         })
     }
 
@@ -711,6 +742,7 @@ impl ResolveResult {
         ResolveResult {
             primary: new_primary,
             affecting_sources: self.affecting_sources.clone(),
+            exports_field_traces: self.exports_field_traces.clone(),
         }
     }
 
@@ -903,6 +935,7 @@ impl ResolveResult {
         Ok(ResolveResult {
             primary: new_primary,
             affecting_sources: this.affecting_sources.clone(),
+            exports_field_traces: this.exports_field_traces.clone(),
         }
         .into())
     }
@@ -928,9 +961,36 @@ impl ResolveResult {
         Ok(ResolveResult {
             primary: new_primary,
             affecting_sources: this.affecting_sources.clone(),
+            exports_field_traces: this.exports_field_traces.clone(),
         }
         .into())
     }
+
+    /// Returns a new [ResolveResult] where all resolved [FileSource]s have the
+    /// passed `fragment`, e.g. `#icon` for a request like `./x.svg#icon`.
+    #[turbo_tasks::function]
+    pub async fn with_fragment(self: Vc<Self>, fragment: Vc<String>) -> Result<Vc<Self>> {
+        let this = self.await?;
+        let result = this
+            .map(
+                |source| async move {
+                    let Some(file_source) =
+                        Vc::try_resolve_downcast_type::<FileSource>(source).await?
+                    else {
+                        return Ok(source);
+                    };
+                    let file_source = file_source.await?;
+                    Ok(Vc::upcast(FileSource::new_with_query_and_fragment(
+                        file_source.path,
+                        file_source.query,
+                        fragment,
+                    )))
+                },
+                |source| async move { Ok(source) },
+            )
+            .await?;
+        Ok(result.cell())
+    }
 }
 
 #[turbo_tasks::value(transparent)]
@@ -1146,44 +1206,52 @@ async fn find_package(
     let mut packages = vec![];
     let mut affecting_sources = vec![];
     let options = options.await?;
+    // `dir_exists` and `any_exists` return the real path of a directory.
+    let keep_path = |path: Vc<FileSystemPath>, real_path: Vc<FileSystemPath>| {
+        if options.preserve_symlinks {
+            path
+        } else {
+            real_path
+        }
+    };
     for resolve_modules in &options.modules {
         match resolve_modules {
-            ResolveModules::Nested(root_vc, names) => {
-                let mut lookup_path = lookup_path;
-                let mut lookup_path_value = lookup_path.await?;
-                // For clippy -- This explicit deref is necessary
-                let root = &*root_vc.await?;
-                while lookup_path_value.is_inside_ref(root) {
-                    for name in names.iter() {
-                        let fs_path = lookup_path.join(name.clone());
-                        if let Some(fs_path) = dir_exists(fs_path, &mut affecting_sources).await? {
-                            let fs_path = fs_path.join(package_name.clone());
-                            if let Some(fs_path) =
-                                dir_exists(fs_path, &mut affecting_sources).await?
-                            {
-                                packages.push(FindPackageItem::PackageDirectory(fs_path));
-                            }
-                        }
-                    }
-                    lookup_path = lookup_path.parent().resolve().await?;
-                    let new_context_value = lookup_path.await?;
-                    if *new_context_value == *lookup_path_value {
-                        break;
-                    }
-                    lookup_path_value = new_context_value;
+            ResolveModules::Nested(root, names) => {
+                // Directories without a module directory can't contain packages, so the
+                // lookup continues from the closest one, which is shared by all lookup
+                // paths below it.
+                let nearest =
+                    modules_lookup_path(lookup_path, *root, names.clone(), options.boundary)
+                        .await?;
+                affecting_sources.extend(nearest.affecting_sources.iter().copied());
+                if let Some(lookup_path) = nearest.lookup_path {
+                    let result = find_nested_package(
+                        lookup_path,
+                        *root,
+                        names.clone(),
+                        options.boundary,
+                        package_name.clone(),
+                        options.preserve_symlinks,
+                    )
+                    .await?;
+                    packages.extend(result.packages.iter().copied());
+                    affecting_sources.extend(result.affecting_sources.iter().copied());
                 }
             }
             ResolveModules::Path(context) => {
-                let package_dir = context.join(package_name.clone());
-                if let Some((ty, package_dir)) =
+                let package_dir = context.join(package_name.clone()).resolve().await?;
+                if let Some((ty, real_package_dir)) =
                     any_exists(package_dir, &mut affecting_sources).await?
                 {
                     match ty {
                         FileSystemEntryType::Directory => {
-                            packages.push(FindPackageItem::PackageDirectory(package_dir));
+                            packages.push(FindPackageItem::PackageDirectory(keep_path(
+                                package_dir,
+                                real_package_dir,
+                            )));
                         }
                         FileSystemEntryType::File => {
-                            packages.push(FindPackageItem::PackageFile(package_dir));
+                            packages.push(FindPackageItem::PackageFile(real_package_dir));
                         }
                         _ => {}
                     }
@@ -1197,6 +1265,124 @@ async fn find_package(
                 }
             }
             ResolveModules::Registry(_, _) => todo!(),
+            // Bundled modules are handled by `resolve_module_request`
+            ResolveModules::Bundled(_) => {}
+        }
+    }
+    Ok(FindPackageResult::cell(FindPackageResult {
+        packages,
+        affecting_sources,
+    }))
+}
+
+/// The closest directory to a lookup path which contains a module directory.
+#[turbo_tasks::value]
+struct ModulesLookupPath {
+    lookup_path: Option<Vc<FileSystemPath>>,
+    affecting_sources: Vec<Vc<Box<dyn Source>>>,
+}
+
+/// Walks up from `lookup_path` to the closest directory inside of `root` (and
+/// `boundary`) which contains one of the module directories `names`.
+#[turbo_tasks::function]
+async fn modules_lookup_path(
+    lookup_path: Vc<FileSystemPath>,
+    root: Vc<FileSystemPath>,
+    names: Vec<String>,
+    boundary: Option<Vc<FileSystemPath>>,
+) -> Result<Vc<ModulesLookupPath>> {
+    let mut affecting_sources = vec![];
+    let mut lookup_path = lookup_path;
+    let mut lookup_path_value = lookup_path.await?;
+    // For clippy -- This explicit deref is necessary
+    let root = &*root.await?;
+    let boundary = match boundary {
+        Some(boundary) => Some(boundary.await?),
+        None => None,
+    };
+    while lookup_path_value.is_inside_ref(root)
+        && boundary.as_ref().map_or(true, |boundary| {
+            lookup_path_value.is_inside_or_equal_ref(boundary)
+        })
+    {
+        // The sources of the directory found are reported by `find_nested_package`.
+        let skipped_affecting_sources = affecting_sources.len();
+        for name in names.iter() {
+            let fs_path = lookup_path.join(name.clone()).resolve().await?;
+            if dir_exists(fs_path, &mut affecting_sources).await?.is_some() {
+                affecting_sources.truncate(skipped_affecting_sources);
+                return Ok(ModulesLookupPath {
+                    lookup_path: Some(lookup_path),
+                    affecting_sources,
+                }
+                .cell());
+            }
+        }
+        lookup_path = lookup_path.parent().resolve().await?;
+        let new_context_value = lookup_path.await?;
+        if *new_context_value == *lookup_path_value {
+            break;
+        }
+        lookup_path_value = new_context_value;
+    }
+    Ok(ModulesLookupPath {
+        lookup_path: None,
+        affecting_sources,
+    }
+    .cell())
+}
+
+/// Finds the package `package_name` in the module directories of
+/// `lookup_path` and of all of its parents which have module directories.
+#[turbo_tasks::function]
+async fn find_nested_package(
+    lookup_path: Vc<FileSystemPath>,
+    root: Vc<FileSystemPath>,
+    names: Vec<String>,
+    boundary: Option<Vc<FileSystemPath>>,
+    package_name: String,
+    preserve_symlinks: bool,
+) -> Result<Vc<FindPackageResult>> {
+    let mut packages = vec![];
+    let mut affecting_sources = vec![];
+    // `dir_exists` returns the real path of a directory.
+    let keep_path = |path: Vc<FileSystemPath>, real_path: Vc<FileSystemPath>| {
+        if preserve_symlinks {
+            path
+        } else {
+            real_path
+        }
+    };
+    for name in names.iter() {
+        let fs_path = lookup_path.join(name.clone()).resolve().await?;
+        if let Some(real_path) = dir_exists(fs_path, &mut affecting_sources).await? {
+            let fs_path = keep_path(fs_path, real_path)
+                .join(package_name.clone())
+                .resolve()
+                .await?;
+            if let Some(real_path) = dir_exists(fs_path, &mut affecting_sources).await? {
+                packages.push(FindPackageItem::PackageDirectory(keep_path(
+                    fs_path, real_path,
+                )));
+            }
+        }
+    }
+    let parent = lookup_path.parent().resolve().await?;
+    if *parent.await? != *lookup_path.await? {
+        let nearest = modules_lookup_path(parent, root, names.clone(), boundary).await?;
+        affecting_sources.extend(nearest.affecting_sources.iter().copied());
+        if let Some(lookup_path) = nearest.lookup_path {
+            let result = find_nested_package(
+                lookup_path,
+                root,
+                names,
+                boundary,
+                package_name,
+                preserve_symlinks,
+            )
+            .await?;
+            packages.extend(result.packages.iter().copied());
+            affecting_sources.extend(result.affecting_sources.iter().copied());
         }
     }
     Ok(FindPackageResult::cell(FindPackageResult {
@@ -1453,6 +1639,7 @@ async fn handle_resolve_plugins(
     Ok(ResolveResult {
         primary: new_primary,
         affecting_sources,
+        exports_field_traces: result_value.exports_field_traces.clone(),
     }
     .cell())
 }
@@ -1564,7 +1751,7 @@ async fn resolve_internal_inline(
                         }
                         PatternMatch::Directory(matched_pattern, path) => {
                             results.push(
-                                resolve_into_folder(*path, options)
+                                resolve_into_folder(*path, options, *query)
                                     .with_request(matched_pattern.clone()),
                             );
                         }
@@ -1577,6 +1764,7 @@ async fn resolve_internal_inline(
                 path,
                 query,
                 force_in_lookup_dir,
+                ..
             } => {
                 resolve_relative_request(
                     lookup_path,
@@ -1593,6 +1781,7 @@ async fn resolve_internal_inline(
                 module,
                 path,
                 query,
+                ..
             } => {
                 resolve_module_request(
                     lookup_path,
@@ -1605,7 +1794,7 @@ async fn resolve_internal_inline(
                 )
                 .await?
             }
-            Request::ServerRelative { path, query } => {
+            Request::ServerRelative { path, query, .. } => {
                 let mut new_pat = path.clone();
                 new_pat.push_front(".".to_string().into());
                 let relative = Request::relative(Value::new(new_pat), *query, true);
@@ -1633,7 +1822,7 @@ async fn resolve_internal_inline(
                 )
                 .await?
             }
-            Request::Windows { path: _, query: _ } => {
+            Request::Windows { .. } => {
                 ResolvingIssue {
                     severity: IssueSeverity::Error.cell(),
                     request_type: "windows import: not implemented yet".to_string(),
@@ -1649,7 +1838,7 @@ async fn resolve_internal_inline(
                 ResolveResult::unresolveable().into()
             }
             Request::Empty => ResolveResult::unresolveable().into(),
-            Request::PackageInternal { path } => {
+            Request::PackageInternal { path, query, .. } => {
                 let options_value = options.await?;
                 let (conditions, unspecified_conditions) = options_value
                     .in_package
@@ -1669,6 +1858,7 @@ async fn resolve_internal_inline(
                     path,
                     &conditions,
                     &unspecified_conditions,
+                    *query,
                 )
                 .await?
             }
@@ -1699,6 +1889,23 @@ async fn resolve_internal_inline(
             }
         };
 
+        // A `#` in the middle of a request starts a fragment, which is kept on
+        // the resolved sources. If the request doesn't resolve without it, the
+        // `#` is part of a file name, e.g. `./icon#dark.svg`.
+        let fragment = request.fragment();
+        let result = if fragment.await?.is_empty() {
+            result
+        } else if *result.is_unresolveable().await? {
+            resolve_internal_boxed(
+                lookup_path,
+                request.with_fragment_in_path().resolve().await?,
+                options,
+            )
+            .await?
+        } else {
+            result.with_fragment(fragment)
+        };
+
         // Apply fallback import mappings if provided
         if let Some(import_map) = &options_value.fallback_import_map {
             if *result.is_unresolveable().await? {
@@ -1728,46 +1935,50 @@ async fn resolve_internal_inline(
 async fn resolve_into_folder(
     package_path: Vc<FileSystemPath>,
     options: Vc<ResolveOptions>,
+    query: Vc<String>,
 ) -> Result<Vc<ResolveResult>> {
     let package_json_path = package_path.join("package.json".to_string());
     let options_value = options.await?;
 
     for resolve_into_package in options_value.into_package.iter() {
-        match resolve_into_package {
-            ResolveIntoPackage::MainField { field: name } => {
-                if let Some(package_json) = &*read_package_json(package_json_path).await? {
-                    if let Some(field_value) = package_json[name].as_str() {
-                        let normalized_request = normalize_request(field_value);
-                        if normalized_request.is_empty()
-                            || normalized_request == "."
-                            || normalized_request == "./"
-                        {
-                            continue;
-                        }
-                        let request = Request::parse(Value::new(normalized_request.into()));
+        let fields = match resolve_into_package {
+            ResolveIntoPackage::MainField { field } => std::slice::from_ref(field),
+            ResolveIntoPackage::MainFields { fields } => fields,
+            ResolveIntoPackage::ExportsField { .. } => continue,
+        };
+        for name in fields {
+            if let Some(package_json) = &*read_package_json(package_json_path).await? {
+                if let Some(field_value) = package_json[name].as_str() {
+                    let normalized_request = normalize_request(field_value);
+                    if normalized_request.is_empty()
+                        || normalized_request == "."
+                        || normalized_request == "./"
+                    {
+                        continue;
+                    }
+                    let request =
+                        Request::parse(Value::new(normalized_request.into())).with_query(query);
 
-                        // main field will always resolve not fully specified
-                        let options = if options_value.fully_specified {
-                            options.with_fully_specified(false).resolve().await?
-                        } else {
-                            options
-                        };
-                        let result = &*resolve_internal_inline(package_path, request, options)
-                            .await?
-                            .await?;
-                        // we are not that strict when a main field fails to resolve
-                        // we continue to try other alternatives
-                        if !result.is_unresolveable_ref() {
-                            let mut result = result.with_request_ref(".".to_string());
-                            result.add_affecting_source_ref(Vc::upcast(FileSource::new(
-                                package_json_path,
-                            )));
-                            return Ok(result.into());
-                        }
+                    // main field will always resolve not fully specified
+                    let options = if options_value.fully_specified {
+                        options.with_fully_specified(false).resolve().await?
+                    } else {
+                        options
+                    };
+                    let result = &*resolve_internal_inline(package_path, request, options)
+                        .await?
+                        .await?;
+                    // we are not that strict when a main field fails to resolve
+                    // we continue to try other alternatives
+                    if !result.is_unresolveable_ref() {
+                        let mut result = result.with_request_ref(".".to_string());
+                        result.add_affecting_source_ref(Vc::upcast(FileSource::new(
+                            package_json_path,
+                        )));
+                        return Ok(result.into());
                     }
-                };
-            }
-            ResolveIntoPackage::ExportsField { .. } => {}
+                }
+            };
         }
     }
 
@@ -1787,7 +1998,7 @@ async fn resolve_into_folder(
         ),
     };
 
-    let request = Request::parse(Value::new(pattern));
+    let request = Request::parse(Value::new(pattern)).with_query(query);
 
     Ok(
         resolve_internal_inline(package_path, request.resolve().await?, options)
@@ -1825,6 +2036,39 @@ async fn resolve_relative_request(
         return Ok(result);
     }
 
+    if let Some(path) = path_pattern.as_string() {
+        for (extension, aliases) in &options_value.extension_alias {
+            let Some(stem) = path.strip_suffix(extension.as_str()) else {
+                continue;
+            };
+            for alias in aliases {
+                let matches = read_matches(
+                    lookup_path,
+                    "".to_string(),
+                    force_in_lookup_dir,
+                    Pattern::new(Pattern::Constant(format!("{stem}{alias}")))
+                        .resolve()
+                        .await?,
+                )
+                .await?;
+                for m in matches.iter() {
+                    if let PatternMatch::File(_, aliased_path) = m {
+                        return resolved(
+                            RequestKey::new(path.to_string()),
+                            *aliased_path,
+                            lookup_path,
+                            request,
+                            options_value,
+                            options,
+                            query,
+                        )
+                        .await;
+                    }
+                }
+            }
+        }
+    }
+
     let mut new_path = path_pattern.clone();
     if !options_value.fully_specified {
         // Add the extensions as alternatives to the path
@@ -1895,7 +2139,9 @@ async fn resolve_relative_request(
     // Directory matches must be resolved AFTER file matches
     for m in matches.iter() {
         if let PatternMatch::Directory(matched_pattern, path) = m {
-            results.push(resolve_into_folder(*path, options).with_request(matched_pattern.clone()));
+            results.push(
+                resolve_into_folder(*path, options, query).with_request(matched_pattern.clone()),
+            );
         }
     }
 
@@ -1954,11 +2200,13 @@ async fn apply_in_package(
         let refs = refs.clone();
         let request_key = RequestKey::new(request.clone());
 
+        // `false` replaces the module with an empty one, e.g. to exclude Node.js
+        // only modules from browser builds.
         if value.as_bool() == Some(false) {
             return Ok(Some(
                 ResolveResult::primary_with_affecting_sources(
                     request_key,
-                    ResolveResultItem::Ignore,
+                    ResolveResultItem::Empty,
                     refs,
                 )
                 .cell(),
@@ -2027,6 +2275,56 @@ async fn resolve_module_request(
         return Ok(result);
     }
 
+    if path.is_match("") {
+        for resolve_modules in &options_value.modules {
+            if let ResolveModules::Bundled(sources) = resolve_modules {
+                if let Some(source) = sources.await?.get(module) {
+                    return Ok(ResolveResult::source_with_key(
+                        RequestKey::new(module.to_string()),
+                        *source,
+                    )
+                    .into());
+                }
+            }
+        }
+    }
+
+    // A package can import itself by its name through its exports field.
+    if options_value
+        .into_package
+        .iter()
+        .any(|into_package| matches!(into_package, ResolveIntoPackage::ExportsField { .. }))
+    {
+        if let FindContextFileResult::Found(package_json_path, refs) =
+            &*find_context_file(lookup_path, package_json().resolve().await?).await?
+        {
+            let is_self_reference = match &*read_package_json(*package_json_path).await? {
+                Some(package_json) => {
+                    package_json.get("name").and_then(|name| name.as_str()) == Some(module)
+                }
+                None => false,
+            };
+            if is_self_reference
+                && matches!(
+                    *exports_field(*package_json_path).await?,
+                    ExportsFieldResult::Some(_)
+                )
+            {
+                return Ok(resolve_into_package(
+                    Value::new(path.clone()),
+                    package_json_path.parent().resolve().await?,
+                    query,
+                    options,
+                )
+                .with_replaced_request_key(
+                    ".".to_string(),
+                    Value::new(RequestKey::new(module.to_string())),
+                )
+                .with_affecting_sources(refs.clone()));
+            }
+        }
+    }
+
     let mut results = vec![];
 
     let result = find_package(
@@ -2057,6 +2355,14 @@ async fn resolve_module_request(
                     query,
                     options,
                 ));
+                if options_value.strict_exports
+                    && matches!(
+                        *exports_field(package_path.join("package.json".to_string())).await?,
+                        ExportsFieldResult::Some(_)
+                    )
+                {
+                    break;
+                }
             }
             FindPackageItem::PackageFile(package_path) => {
                 if path.is_match("") {
@@ -2084,12 +2390,10 @@ async fn resolve_module_request(
             );
 
     if options_value.prefer_relative {
+        // The path of the module request starts with a `/` unless it's empty,
+        // so `foo` becomes `./foo` and `foo/bar` becomes `./foo/bar`.
         let module_prefix = format!("./{module}");
-        let pattern = Pattern::concat([
-            module_prefix.clone().into(),
-            "/".to_string().into(),
-            path.clone(),
-        ]);
+        let pattern = Pattern::concat([module_prefix.clone().into(), path.clone()]);
         let relative = Request::relative(Value::new(pattern), query, true);
         let relative_result =
             resolve_internal_boxed(lookup_path, relative.resolve().await?, options).await?;
@@ -2112,6 +2416,12 @@ async fn resolve_into_package(
     options: Vc<ResolveOptions>,
 ) -> Result<Vc<ResolveResult>> {
     let path = path.into_value();
+    let options = match &options.await?.package_extensions {
+        Some(package_extensions) => options.with_extensions(package_extensions.clone()),
+        None => options,
+    }
+    .resolve()
+    .await?;
     let options_value = options.await?;
     let mut results = Vec::new();
 
@@ -2121,7 +2431,7 @@ async fn resolve_into_package(
     for resolve_into_package in options_value.into_package.iter() {
         match resolve_into_package {
             // handled by the `resolve_into_folder` call below
-            ResolveIntoPackage::MainField { .. } => {}
+            ResolveIntoPackage::MainField { .. } | ResolveIntoPackage::MainFields { .. } => {}
             ResolveIntoPackage::ExportsField {
                 conditions,
                 unspecified_conditions,
@@ -2166,7 +2476,7 @@ async fn resolve_into_package(
 
     // apply main field(s) or fallback to index.js if there's no subpath
     if is_root_match {
-        results.push(resolve_into_folder(package_path, options));
+        results.push(resolve_into_folder(package_path, options, query));
     }
 
     if could_match_others {
@@ -2258,7 +2568,15 @@ async fn resolved(
     options: Vc<ResolveOptions>,
     query: Vc<String>,
 ) -> Result<Vc<ResolveResult>> {
-    let RealPathResult { path, symlinks } = &*fs_path.realpath_with_links().await?;
+    let RealPathResult {
+        path: real_path,
+        symlinks,
+    } = &*fs_path.realpath_with_links().await?;
+    let path = if options_value.preserve_symlinks {
+        fs_path.resolve().await?
+    } else {
+        *real_path
+    };
 
     let path_ref = &*path.await?;
     // Check alias field for path aliases first
@@ -2276,7 +2594,7 @@ async fn resolved(
 
     if let Some(resolved_map) = options_value.resolved_map {
         let result = resolved_map
-            .lookup(*path, original_context, original_request)
+            .lookup(path, original_context, original_request)
             .await?;
 
         let resolved_result = resolve_import_map_result(
@@ -2296,7 +2614,7 @@ async fn resolved(
 
     Ok(ResolveResult::source_with_affecting_sources(
         request_key,
-        Vc::upcast(FileSource::new_with_query(*path, query)),
+        Vc::upcast(FileSource::new_with_query(path, query)),
         symlinks
             .iter()
             .copied()
@@ -2319,44 +2637,253 @@ async fn handle_exports_imports_field(
 ) -> Result<Vc<ResolveResult>> {
     let mut results = Vec::new();
     let mut conditions_state = HashMap::new();
-
-    let req = format!("{}{}", path, &*query.await?);
-    let values = exports_imports_field
-        .lookup(&req)
+    let field = if path.starts_with('#') {
+        "imports"
+    } else {
+        "exports"
+    };
+    let mut blocked = false;
+
+    // The query is not part of the subpath. It's matched without it and added
+    // to the resolved request instead, so `pkg/x?raw` resolves like `pkg/x`.
+    // Only the most specific matching key applies. Less specific keys are not
+    // used as a fallback when the conditions of the best match don't match or
+    // when it's excluded.
+    let value = exports_imports_field
+        .lookup(path)
+        .next()
         .map(AliasMatch::try_into_self)
-        .collect::<Result<Vec<_>>>()?;
+        .transpose()?;
 
-    for value in values.iter() {
-        if value.add_results(
+    if let Some(value) = &value {
+        let matched = value.add_results(
             conditions,
             unspecified_conditions,
             &mut conditions_state,
             &mut results,
-        ) {
-            break;
+        );
+        // The subpath is explicitly blocked with a `null` target.
+        blocked = matched && results.is_empty();
+        if blocked {
+            ResolvingIssue {
+                severity: IssueSeverity::Error.cell(),
+                file_path: package_json_path,
+                request_type: format!("{field} field"),
+                request: Request::parse(Value::new(Pattern::Constant(path.to_string()))),
+                resolve_options: options,
+                error_message: Some(format!(
+                    "the subpath `{path}` is blocked by a `null` target in the {field} field of {}",
+                    package_json_path.to_string().await?
+                )),
+                source: None,
+            }
+            .cell()
+            .emit();
         }
     }
 
     let mut resolved_results = Vec::new();
-    for (result_path, conditions) in results {
-        if let Some(result_path) = normalize_path(result_path) {
-            let request = Request::parse(Value::new(format!("./{}", result_path).into()));
-            let resolve_result = resolve_internal_boxed(package_path, request, options).await?;
-            if conditions.is_empty() {
-                resolved_results.push(resolve_result.with_request(path.to_string()));
+    let group_count = results.len();
+    for (i, group) in results.into_iter().enumerate() {
+        let mut group_results = Vec::new();
+        for (result_path, conditions) in group {
+            // Targets in the imports field which aren't relative refer to packages,
+            // e.g. `"#dep": "dep"`.
+            let is_package_target = path.starts_with('#')
+                && !result_path.starts_with("./")
+                && !result_path.starts_with("../");
+            let request = if is_package_target {
+                Some(Request::parse(Value::new(result_path.to_string().into())))
             } else {
-                let mut resolve_result = resolve_result.await?.with_request_ref(path.to_string());
-                resolve_result.add_conditions(conditions);
-                resolved_results.push(resolve_result.cell());
+                normalize_path(result_path).map(|result_path| {
+                    Request::parse(Value::new(format!("./{}", result_path).into()))
+                })
+            };
+            if let Some(request) = request {
+                let request = request.with_query(query);
+                let resolve_result = resolve_internal_boxed(package_path, request, options).await?;
+                if conditions.is_empty() {
+                    group_results.push(resolve_result.with_request(path.to_string()));
+                } else {
+                    let mut resolve_result =
+                        resolve_result.await?.with_request_ref(path.to_string());
+                    resolve_result.add_conditions(conditions);
+                    group_results.push(resolve_result.cell());
+                }
             }
         }
+        // Later groups are fallbacks from array targets, which are only used
+        // when none of the targets of this group exist. Unresolved results are
+        // kept for their affecting sources.
+        let resolved = i + 1 == group_count
+            || !*merge_results(group_results.clone())
+                .is_unresolveable()
+                .await?;
+        resolved_results.extend(group_results);
+        if resolved {
+            break;
+        }
+    }
+
+    if !blocked
+        && options.await?.exports_field_diagnostics
+        && *merge_results(resolved_results.clone())
+            .is_unresolveable()
+            .await?
+    {
+        ResolvingIssue {
+            severity: IssueSeverity::Error.cell(),
+            file_path: package_json_path,
+            request_type: format!("{field} field"),
+            request: Request::parse(Value::new(Pattern::Constant(path.to_string()))),
+            resolve_options: options,
+            error_message: Some(
+                exports_imports_field_diagnostic(
+                    field,
+                    package_json_path,
+                    exports_imports_field,
+                    path,
+                    conditions,
+                    unspecified_conditions,
+                )
+                .await?,
+            ),
+            source: None,
+        }
+        .cell()
+        .emit();
     }
 
     // other options do not apply anymore when an exports field exist
-    Ok(merge_results_with_affecting_sources(
+    let result = merge_results_with_affecting_sources(
         resolved_results,
         vec![Vc::upcast(FileSource::new(package_json_path))],
-    ))
+    );
+
+    if !options.await?.exports_field_trace {
+        return Ok(result);
+    }
+    let mut matched = Vec::new();
+    let target = value
+        .as_ref()
+        .and_then(|value| value.trace(conditions, unspecified_conditions, &mut matched))
+        .flatten();
+    let mut result = result.await?.clone_value();
+    result.exports_field_traces.push(ExportsFieldTrace {
+        package_json_path,
+        subpath: path.to_string(),
+        conditions: matched.into_iter().map(|c| c.to_string()).collect(),
+        target: target.map(|target| target.to_string()),
+    });
+    Ok(result.cell())
+}
+
+/// Describes why `path` couldn't be resolved with an exports or imports
+/// field: the available subpaths, the ones closest to `path`, and the
+/// conditions which were tried.
+async fn exports_imports_field_diagnostic(
+    field: &str,
+    package_json_path: Vc<FileSystemPath>,
+    exports_imports_field: &AliasMap<SubpathValue>,
+    path: &str,
+    conditions: &BTreeMap<String, ConditionValue>,
+    unspecified_conditions: &ConditionValue,
+) -> Result<String> {
+    let package_name = match &*read_package_json(package_json_path).await? {
+        Some(package_json) => package_json
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(|name| name.to_string()),
+        None => None,
+    };
+    let package = match package_name {
+        Some(name) => format!("package `{name}`"),
+        None => package_json_path.to_string().await?.to_string(),
+    };
+
+    let subpaths = exports_imports_field
+        .into_iter()
+        .map(|(pattern, _)| match pattern {
+            AliasPattern::Exact(subpath) => subpath,
+            AliasPattern::Wildcard { prefix, suffix } => format!("{prefix}*{suffix}"),
+        })
+        .collect::<Vec<_>>();
+    // The subpaths sharing the longest prefix with the requested one.
+    let common_prefix_len = |subpath: &str| {
+        subpath
+            .chars()
+            .zip(path.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let nearest_len = subpaths
+        .iter()
+        .map(|subpath| common_prefix_len(subpath))
+        .max()
+        .unwrap_or_default();
+    let nearest = subpaths
+        .iter()
+        .filter(|subpath| common_prefix_len(subpath) == nearest_len)
+        .collect::<Vec<_>>();
+
+    let conditions_with = |value: ConditionValue| {
+        let names = conditions
+            .iter()
+            .filter(|(_, condition_value)| **condition_value == value)
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            "(none)".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+    let unspecified_conditions = match unspecified_conditions {
+        ConditionValue::Set => "set",
+        ConditionValue::Unset => "unset",
+        ConditionValue::Negated => "negated",
+        ConditionValue::Unknown => "unknown",
+    };
+
+    let mut diagnostic = String::new();
+    writeln!(
+        diagnostic,
+        "`{path}` could not be resolved with the {field} field of {package}"
+    )?;
+    writeln!(diagnostic, "Available subpaths: {}", subpaths.join(", "))?;
+    if nearest_len > 0 {
+        writeln!(
+            diagnostic,
+            "Nearest matching subpaths: {}",
+            nearest
+                .iter()
+                .map(|subpath| subpath.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+    writeln!(
+        diagnostic,
+        "Set conditions: {}",
+        conditions_with(ConditionValue::Set)
+    )?;
+    writeln!(
+        diagnostic,
+        "Unset conditions: {}",
+        conditions_with(ConditionValue::Unset)
+    )?;
+    writeln!(
+        diagnostic,
+        "Negated conditions: {}",
+        conditions_with(ConditionValue::Negated)
+    )?;
+    writeln!(
+        diagnostic,
+        "Unknown conditions: {}",
+        conditions_with(ConditionValue::Unknown)
+    )?;
+    write!(diagnostic, "Other conditions are {unspecified_conditions}")?;
+    Ok(diagnostic)
 }
 
 /// Resolves a `#dep` import using the containing package.json's `imports`
@@ -2370,6 +2897,7 @@ async fn resolve_package_internal_with_imports_field(
     pattern: &Pattern,
     conditions: &BTreeMap<String, ConditionValue>,
     unspecified_conditions: &ConditionValue,
+    query: Vc<String>,
 ) -> Result<Vc<ResolveResult>> {
     let Pattern::Constant(specifier) = pattern else {
         bail!("PackageInternal requests can only be Constant strings");
@@ -2404,7 +2932,7 @@ async fn resolve_package_internal_with_imports_field(
         specifier,
         conditions,
         unspecified_conditions,
-        Vc::<String>::default(),
+        query,
     )
     .await
 }
@@ -2420,11 +2948,12 @@ pub async fn handle_resolve_error(
 ) -> Result<Vc<ModuleResolveResult>> {
     Ok(match result.is_unresolveable().await {
         Ok(unresolveable) => {
+            let reference_type = reference_type.into_value();
             if *unresolveable {
                 ResolvingIssue {
                     severity,
                     file_path: origin_path,
-                    request_type: format!("{} request", reference_type.into_value()),
+                    request_type: format!("{reference_type} request"),
                     request,
                     resolve_options,
                     error_message: None,
@@ -2432,6 +2961,18 @@ pub async fn handle_resolve_error(
                 }
                 .cell()
                 .emit();
+            } else if is_static_esm_import(&reference_type)
+                && resolve_options.await?.strict_json_imports
+            {
+                emit_json_import_attributes_issue(
+                    result,
+                    &reference_type,
+                    origin_path,
+                    request,
+                    resolve_options,
+                    source,
+                )
+                .await?;
             }
             result
         }
@@ -2452,6 +2993,55 @@ pub async fn handle_resolve_error(
     })
 }
 
+/// Returns true for a static ES module import or re-export. Dynamic
+/// `import()` calls aren't included, as their import attributes aren't
+/// analyzed.
+fn is_static_esm_import(reference_type: &ReferenceType) -> bool {
+    matches!(
+        reference_type,
+        ReferenceType::EcmaScriptModules(
+            EcmaScriptModulesReferenceSubType::Import
+                | EcmaScriptModulesReferenceSubType::ImportPart(_)
+                | EcmaScriptModulesReferenceSubType::ReExport
+                | EcmaScriptModulesReferenceSubType::ExportAll
+        )
+    )
+}
+
+/// Reports a static ES module import of a JSON module, which needs a
+/// `type: "json"` import attribute. See [ResolveOptions::strict_json_imports].
+async fn emit_json_import_attributes_issue(
+    result: Vc<ModuleResolveResult>,
+    reference_type: &ReferenceType,
+    origin_path: Vc<FileSystemPath>,
+    request: Vc<Request>,
+    resolve_options: Vc<ResolveOptions>,
+    source: Option<Vc<IssueSource>>,
+) -> Result<()> {
+    for module in result.primary_modules().await?.iter() {
+        if module.ident().path().await?.extension_ref() != Some("json") {
+            continue;
+        }
+        ResolvingIssue {
+            severity: IssueSeverity::Error.cell(),
+            file_path: origin_path,
+            request_type: format!("{reference_type} request"),
+            request,
+            resolve_options,
+            error_message: Some(
+                "JSON modules need to be imported with a `type: \"json\"` import attribute, e.g. \
+                 `import data from \"./data.json\" with { type: \"json\" }`"
+                    .to_string(),
+            ),
+            source,
+        }
+        .cell()
+        .emit();
+        break;
+    }
+    Ok(())
+}
+
 // TODO this should become a TaskInput instead of a Vc
 /// ModulePart represents a part of a module.
 ///
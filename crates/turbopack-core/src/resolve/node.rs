@@ -1,74 +1,374 @@
-use turbo_tasks::Vc;
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, Vc};
 use turbo_tasks_fs::FileSystemPath;
 
 use super::options::{
-    ConditionValue, ResolutionConditions, ResolveInPackage, ResolveIntoPackage, ResolveModules,
-    ResolveOptions,
+    ConditionValue, ImportMap, ImportMapping, ResolutionConditions, ResolveInPackage,
+    ResolveIntoPackage, ResolveModules, ResolveOptions,
 };
 
-#[turbo_tasks::function]
-pub fn node_cjs_resolve_options(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
-    let conditions: ResolutionConditions = [
-        ("node".to_string(), ConditionValue::Set),
-        ("require".to_string(), ConditionValue::Set),
+/// Selects between the `development` and `production` conditions.
+#[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ResolveMode {
+    Development,
+    Production,
+}
+
+/// Customizes the resolve options created by
+/// [node_cjs_resolve_options_with_context] and
+/// [node_esm_resolve_options_with_context].
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct NodeResolveOptionsContext {
+    /// Resolve for a browser target. This sets the `browser` condition and
+    /// consults the [browser] field of package.json files, both in its string
+    /// form (an alternative entry point) and in its object form (remapping
+    /// individual files of the package).
+    ///
+    /// [browser]: https://github.com/defunctzombie/package-browser-field-spec
+    pub browser: bool,
+    /// The conditions which describe the target environment, replacing the
+    /// default `node` condition, e.g. `electron` and `node` for the main
+    /// process of an Electron app. `node` is only set when it's part of the
+    /// list. The `require`/`import`, `types`, `browser`, mode and `default`
+    /// conditions are set as usual.
+    pub environment_conditions: Option<Vec<String>>,
+    /// Additional extensions to try after the default ones, in priority order.
+    /// Extensions which are already part of the list are skipped.
+    pub extensions: Vec<String>,
+    /// Alternative extensions for the extension of relative requests. See
+    /// [ResolveOptions::extension_alias].
+    pub extension_alias: IndexMap<String, Vec<String>>,
+    /// Overrides whether relative requests need to be fully specified. By
+    /// default they need to be for ESM but not for CommonJS. Setting this to
+    /// `false` for ESM matches Node's
+    /// `--experimental-specifier-resolution=node` behavior.
+    pub fully_specified: Option<bool>,
+    /// The directory names to look for packages in, replacing the default
+    /// `node_modules`.
+    pub module_dirs: Option<Vec<String>>,
+    /// Absolute directories to look for packages in, like webpack's
+    /// `resolve.modules` with an absolute path. They are searched after the
+    /// module directories of all ancestors, so packages installed there take
+    /// precedence.
+    pub module_roots: Vec<Vc<FileSystemPath>>,
+    /// Sets either the `development` or the `production` condition, and unsets
+    /// the other one. Without a mode neither condition matches, so exports
+    /// fall through to `default`.
+    pub mode: Option<ResolveMode>,
+    /// The value of conditions in exports and imports fields which are
+    /// neither set nor unset by these options, e.g. custom conditions of a
+    /// package. Defaults to [ConditionValue::Unset]. With
+    /// [ConditionValue::Set], the first such condition matches.
+    pub unspecified_conditions: Option<ConditionValue>,
+    /// Aliases which are applied before resolving a request, like webpack's
+    /// `resolve.alias`. Keys ending with `/` replace that prefix of a request,
+    /// e.g. `"@/"` to `"./src/"`. Keys ending with `$` only match the exact
+    /// request. Other keys match the exact request and requests for files
+    /// inside of it, so `"lib"` also matches `"lib/get"`. More specific keys
+    /// take precedence.
+    ///
+    /// Targets are resolved from the root directory, so they can point
+    /// outside of the importing package. When a target doesn't resolve, the
+    /// original request is resolved instead.
+    pub aliases: IndexMap<String, String>,
+    /// Keeps the paths of symlinks instead of resolving them to their real
+    /// path. See [ResolveOptions::preserve_symlinks].
+    pub preserve_symlinks: bool,
+    /// Resolves module requests like `foo` to a relative file or directory
+    /// `./foo` when it exists, before looking for a package. See
+    /// [ResolveOptions::prefer_relative].
+    pub prefer_relative: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NodeModuleKind {
+    CommonJs,
+    EcmaScript,
+    /// Resolves TypeScript declarations of ES modules.
+    TypeScript,
+    /// Resolves style sheets, e.g. for Sass `@use` rules.
+    Style,
+}
+
+fn node_resolve_options(
+    root: Vc<FileSystemPath>,
+    kind: NodeModuleKind,
+    context: &NodeResolveOptionsContext,
+) -> ResolveOptions {
+    // `require` and `import` are mutually exclusive, so the one which doesn't
+    // match is negated and stays off when the options are merged.
+    let (require, import) = match kind {
+        NodeModuleKind::CommonJs => (ConditionValue::Set, ConditionValue::Negated),
+        NodeModuleKind::EcmaScript | NodeModuleKind::TypeScript => {
+            (ConditionValue::Negated, ConditionValue::Set)
+        }
+        NodeModuleKind::Style => (ConditionValue::Unset, ConditionValue::Unset),
+    };
+    let mut conditions: ResolutionConditions = [
+        ("require".to_string(), require),
+        ("import".to_string(), import),
+        (
+            "types".to_string(),
+            (kind == NodeModuleKind::TypeScript).into(),
+        ),
+        ("browser".to_string(), context.browser.into()),
+        // `default` always matches. Conditions are matched in the order of the
+        // exports field, where `default` is required to come last, so it acts
+        // as the lowest priority fallback.
+        ("default".to_string(), ConditionValue::Set),
     ]
     .into();
-    let extensions = vec![".js".to_string(), ".json".to_string(), ".node".to_string()];
+    if kind == NodeModuleKind::Style {
+        // Style sheets don't run in the target environment.
+        conditions.insert("style".to_string(), ConditionValue::Set);
+        conditions.insert("sass".to_string(), ConditionValue::Set);
+    } else if let Some(environment_conditions) = &context.environment_conditions {
+        for condition in environment_conditions {
+            conditions.insert(condition.clone(), ConditionValue::Set);
+        }
+    } else {
+        conditions.insert("node".to_string(), ConditionValue::Set);
+    }
+    // Both are unset without a mode, so exports fall through to `default`.
+    conditions.insert(
+        "development".to_string(),
+        (context.mode == Some(ResolveMode::Development)).into(),
+    );
+    conditions.insert(
+        "production".to_string(),
+        (context.mode == Some(ResolveMode::Production)).into(),
+    );
+    // `.mjs` and `.cjs` files are always ESM and CommonJS respectively,
+    // regardless of the `type` of the nearest package.json. `.mjs` is not
+    // resolved for `require`, since Node can't `require` ES modules.
+    let module_extension = match kind {
+        NodeModuleKind::CommonJs => Some(".cjs"),
+        NodeModuleKind::EcmaScript | NodeModuleKind::TypeScript => Some(".mjs"),
+        NodeModuleKind::Style => None,
+    };
+    let mut extensions = Vec::new();
+    match kind {
+        // TypeScript sources and declarations take precedence over JavaScript
+        // files next to them.
+        NodeModuleKind::TypeScript => {
+            extensions.extend([".ts", ".tsx", ".d.ts", ".js", ".jsx"].map(String::from))
+        }
+        NodeModuleKind::Style => extensions.extend([".scss", ".sass", ".css"].map(String::from)),
+        NodeModuleKind::CommonJs | NodeModuleKind::EcmaScript => extensions.push(".js".to_string()),
+    }
+    if let Some(module_extension) = module_extension {
+        extensions.extend([module_extension, ".json", ".node"].map(String::from));
+    }
+    for extension in &context.extensions {
+        if !extensions.contains(extension) {
+            extensions.push(extension.clone());
+        }
+    }
+
+    let unspecified_conditions = context
+        .unspecified_conditions
+        .unwrap_or(ConditionValue::Unset);
+    let mut into_package = vec![ResolveIntoPackage::ExportsField {
+        conditions: conditions.clone(),
+        unspecified_conditions,
+    }];
+    // `typings` is an alias of `types` which is supported by TypeScript too.
+    // `typesVersions` is not supported.
+    if kind == NodeModuleKind::TypeScript {
+        for field in ["types", "typings"] {
+            into_package.push(ResolveIntoPackage::MainField {
+                field: field.to_string(),
+            });
+        }
+    }
+    if kind == NodeModuleKind::Style {
+        // The `main` field points to JavaScript, so only the style sheet
+        // fields are used for packages without an exports field.
+        into_package.push(ResolveIntoPackage::MainFields {
+            fields: vec!["sass".to_string(), "style".to_string()],
+        });
+    } else {
+        if context.browser {
+            into_package.push(ResolveIntoPackage::MainField {
+                field: "browser".to_string(),
+            });
+        }
+        // The legacy `module` field points bundlers to an ESM build of
+        // packages without an exports field. It's not meaningful for
+        // `require`.
+        if kind != NodeModuleKind::CommonJs {
+            into_package.push(ResolveIntoPackage::MainField {
+                field: "module".to_string(),
+            });
+        }
+        into_package.push(ResolveIntoPackage::MainField {
+            field: "main".to_string(),
+        });
+    }
+
+    let mut in_package = vec![ResolveInPackage::ImportsField {
+        conditions,
+        unspecified_conditions,
+    }];
+    if context.browser {
+        in_package.push(ResolveInPackage::AliasField("browser".to_string()));
+    }
+
     ResolveOptions {
+        fully_specified: context
+            .fully_specified
+            .unwrap_or(kind == NodeModuleKind::EcmaScript),
         extensions,
-        modules: vec![ResolveModules::Nested(
+        extension_alias: context.extension_alias.clone(),
+        modules: [ResolveModules::Nested(
             root,
-            vec!["node_modules".to_string()],
-        )],
-        into_package: vec![
-            ResolveIntoPackage::ExportsField {
-                conditions: conditions.clone(),
-                unspecified_conditions: ConditionValue::Unset,
-            },
-            ResolveIntoPackage::MainField {
-                field: "main".to_string(),
-            },
-        ],
-        in_package: vec![ResolveInPackage::ImportsField {
-            conditions,
-            unspecified_conditions: ConditionValue::Unset,
-        }],
+            context
+                .module_dirs
+                .clone()
+                .unwrap_or_else(|| vec!["node_modules".to_string()]),
+        )]
+        .into_iter()
+        .chain(
+            context
+                .module_roots
+                .iter()
+                .copied()
+                .map(ResolveModules::Path),
+        )
+        .collect(),
+        into_package,
+        in_package,
         default_files: vec!["index".to_string()],
+        import_map: aliases_import_map(root, &context.aliases),
+        preserve_symlinks: context.preserve_symlinks,
+        prefer_relative: context.prefer_relative,
         ..Default::default()
     }
+}
+
+fn aliases_import_map(
+    root: Vc<FileSystemPath>,
+    aliases: &IndexMap<String, String>,
+) -> Option<Vc<ImportMap>> {
+    if aliases.is_empty() {
+        return None;
+    }
+    let mapping = |target: String| ImportMapping::PrimaryAlternative(target, Some(root)).cell();
+    let mut import_map = ImportMap::empty();
+    for (key, target) in aliases {
+        if let Some(key) = key.strip_suffix('$') {
+            import_map.insert_exact_alias(key, mapping(target.clone()));
+        } else if key.ends_with('/') {
+            import_map.insert_wildcard_alias(key, mapping(format!("{target}*")));
+        } else {
+            import_map.insert_exact_alias(key, mapping(target.clone()));
+            import_map.insert_wildcard_alias(format!("{key}/"), mapping(format!("{target}/*")));
+        }
+    }
+    Some(import_map.cell())
+}
+
+#[turbo_tasks::function]
+pub fn node_cjs_resolve_options(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
+    node_resolve_options(
+        root,
+        NodeModuleKind::CommonJs,
+        &NodeResolveOptionsContext::default(),
+    )
+    .cell()
+}
+
+#[turbo_tasks::function]
+pub async fn node_cjs_resolve_options_with_context(
+    root: Vc<FileSystemPath>,
+    context: Vc<NodeResolveOptionsContext>,
+) -> Result<Vc<ResolveOptions>> {
+    Ok(node_resolve_options(root, NodeModuleKind::CommonJs, &context.await?).cell())
+}
+
+/// Like [node_cjs_resolve_options], but resolves for a browser target.
+#[turbo_tasks::function]
+pub fn node_cjs_resolve_options_for_browser(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
+    node_resolve_options(
+        root,
+        NodeModuleKind::CommonJs,
+        &NodeResolveOptionsContext {
+            browser: true,
+            ..Default::default()
+        },
+    )
     .cell()
 }
 
 #[turbo_tasks::function]
 pub fn node_esm_resolve_options(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
-    let conditions: ResolutionConditions = [
-        ("node".to_string(), ConditionValue::Set),
-        ("import".to_string(), ConditionValue::Set),
-    ]
-    .into();
-    let extensions = vec![".js".to_string(), ".json".to_string(), ".node".to_string()];
-    ResolveOptions {
-        fully_specified: true,
-        extensions,
-        modules: vec![ResolveModules::Nested(
-            root,
-            vec!["node_modules".to_string()],
-        )],
-        into_package: vec![
-            ResolveIntoPackage::ExportsField {
-                conditions: conditions.clone(),
-                unspecified_conditions: ConditionValue::Unset,
-            },
-            ResolveIntoPackage::MainField {
-                field: "main".to_string(),
-            },
-        ],
-        in_package: vec![ResolveInPackage::ImportsField {
-            conditions,
-            unspecified_conditions: ConditionValue::Unset,
-        }],
-        default_files: vec!["index".to_string()],
-        ..Default::default()
-    }
+    node_resolve_options(
+        root,
+        NodeModuleKind::EcmaScript,
+        &NodeResolveOptionsContext::default(),
+    )
+    .cell()
+}
+
+#[turbo_tasks::function]
+pub async fn node_esm_resolve_options_with_context(
+    root: Vc<FileSystemPath>,
+    context: Vc<NodeResolveOptionsContext>,
+) -> Result<Vc<ResolveOptions>> {
+    Ok(node_resolve_options(root, NodeModuleKind::EcmaScript, &context.await?).cell())
+}
+
+/// Like [node_esm_resolve_options], but resolves for a browser target.
+#[turbo_tasks::function]
+pub fn node_esm_resolve_options_for_browser(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
+    node_resolve_options(
+        root,
+        NodeModuleKind::EcmaScript,
+        &NodeResolveOptionsContext {
+            browser: true,
+            ..Default::default()
+        },
+    )
+    .cell()
+}
+
+/// Resolve options for TypeScript. Sets the `types` condition and consults the
+/// `types` and `typings` fields of package.json files before the other
+/// fields. Requests don't need to be fully specified, and TypeScript
+/// extensions are tried before JavaScript ones, so `./dir` resolves to
+/// `./dir/index.ts`.
+#[turbo_tasks::function]
+pub fn node_ts_resolve_options(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
+    node_resolve_options(
+        root,
+        NodeModuleKind::TypeScript,
+        &NodeResolveOptionsContext::default(),
+    )
+    .cell()
+}
+
+#[turbo_tasks::function]
+pub async fn node_ts_resolve_options_with_context(
+    root: Vc<FileSystemPath>,
+    context: Vc<NodeResolveOptionsContext>,
+) -> Result<Vc<ResolveOptions>> {
+    Ok(node_resolve_options(root, NodeModuleKind::TypeScript, &context.await?).cell())
+}
+
+/// Resolve options for style sheets, e.g. Sass `@use` and `@import` rules. Sets
+/// the `style` and `sass` conditions instead of the environment and
+/// `require`/`import` conditions, and resolves `.scss`, `.sass` and `.css`
+/// files. Packages without an exports field are resolved with their `sass` or
+/// `style` field, but not with `main`, which points to JavaScript.
+#[turbo_tasks::function]
+pub fn node_style_resolve_options(root: Vc<FileSystemPath>) -> Vc<ResolveOptions> {
+    node_resolve_options(
+        root,
+        NodeModuleKind::Style,
+        &NodeResolveOptionsContext::default(),
+    )
     .cell()
 }
@@ -1,6 +1,7 @@
 use std::{collections::BTreeMap, future::Future, pin::Pin};
 
 use anyhow::{bail, Result};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
     debug::ValueDebugFormat, trace::TraceRawVcs, TryJoinIterExt, Value, ValueToString, Vc,
@@ -11,12 +12,19 @@ use super::{
     alias_map::{AliasMap, AliasTemplate},
     AliasPattern, ExternalType, ResolveResult, ResolveResultItem,
 };
-use crate::resolve::{parse::Request, plugin::ResolvePlugin};
+use crate::{
+    resolve::{parse::Request, plugin::ResolvePlugin},
+    source::Source,
+};
 
 #[turbo_tasks::value(shared)]
 #[derive(Hash, Debug)]
 pub struct LockedVersions {}
 
+/// Pre-built sources by the name of the module they provide.
+#[turbo_tasks::value(transparent)]
+pub struct BundledSources(IndexMap<String, Vc<Box<dyn Source>>>);
+
 /// A location where to resolve modules.
 #[derive(
     TraceRawVcs, Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize, ValueDebugFormat,
@@ -31,15 +39,43 @@ pub enum ResolveModules {
     /// registry filesystem is assumed to have structure like
     /// @scope/module/version/<path-in-package>
     Registry(Vc<FileSystemPath>, Vc<LockedVersions>),
+    /// modules which are provided without looking at the filesystem, e. g.
+    /// polyfills for node.js builtins. Only a request for exactly the module
+    /// name matches and it takes precedence over all other locations.
+    Bundled(Vc<BundledSources>),
 }
 
+/// The state of a condition in the exports and imports fields.
 #[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ConditionValue {
+    /// The condition matches.
     Set,
+    /// The condition doesn't match.
     Unset,
+    /// The condition is switched off explicitly, e.g. `import` for a
+    /// `require()`. It doesn't match, like [ConditionValue::Unset], but it
+    /// stays off when it's merged with a source where it's set, which keeps
+    /// mutually exclusive conditions exclusive.
+    Negated,
+    /// It's not known whether the condition matches. The targets of both
+    /// branches are resolved and each result records the value of the
+    /// condition it was resolved with.
     Unknown,
 }
 
+impl ConditionValue {
+    /// Combines the values of a condition for two targets which are resolved
+    /// together. A negated condition stays negated. Otherwise the value is
+    /// only known when it's the same for both.
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (ConditionValue::Negated, _) | (_, ConditionValue::Negated) => ConditionValue::Negated,
+            _ if self == other => self,
+            _ => ConditionValue::Unknown,
+        }
+    }
+}
+
 impl From<bool> for ConditionValue {
     fn from(v: bool) -> Self {
         if v {
@@ -50,8 +86,41 @@ impl From<bool> for ConditionValue {
     }
 }
 
+/// The values of the conditions for the exports and imports fields.
+///
+/// This only describes which conditions match. It doesn't define a priority:
+/// conditional targets are tried in the order of the package.json, and the
+/// first one whose condition matches wins.
 pub type ResolutionConditions = BTreeMap<String, ConditionValue>;
 
+/// Layers the conditions of `later` on top of `earlier`, e.g. overrides on
+/// top of base conditions. A condition which is [ConditionValue::Negated] in
+/// either source is negated. A condition which is [ConditionValue::Set] in one
+/// source and [ConditionValue::Unset] in the other is set, regardless of the
+/// order. Otherwise the value of the later source wins. Conditions which are
+/// only in one source keep their value.
+pub fn merge_resolution_conditions(
+    earlier: &ResolutionConditions,
+    later: &ResolutionConditions,
+) -> ResolutionConditions {
+    let mut conditions = earlier.clone();
+    for (condition, &value) in later {
+        conditions
+            .entry(condition.clone())
+            .and_modify(|earlier_value| {
+                *earlier_value = match (*earlier_value, value) {
+                    (ConditionValue::Negated, _) | (_, ConditionValue::Negated) => {
+                        ConditionValue::Negated
+                    }
+                    (ConditionValue::Set, ConditionValue::Unset) => ConditionValue::Set,
+                    _ => value,
+                };
+            })
+            .or_insert(value);
+    }
+    conditions
+}
+
 /// The different ways to resolve a package, as described in package.json.
 #[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum ResolveIntoPackage {
@@ -68,6 +137,12 @@ pub enum ResolveIntoPackage {
     /// [module]: https://esbuild.github.io/api/#main-fields
     /// [browser]: https://esbuild.github.io/api/#main-fields
     MainField { field: String },
+    /// Using the first of several [main]-like fields which is present and
+    /// resolves, in priority order, e.g. `["browser", "module", "main"]`. This
+    /// is equivalent to a [ResolveIntoPackage::MainField] per field.
+    ///
+    /// [main]: https://nodejs.org/api/packages.html#main
+    MainFields { fields: Vec<String> },
 }
 
 // The different ways to resolve a request withing a package
@@ -416,17 +491,72 @@ pub struct ResolveOptions {
     pub fully_specified: bool,
     /// When set, when resolving a module request, try to resolve it as relative
     /// request first.
+    ///
+    /// A module request like `foo` resolves to the file or directory `./foo`
+    /// relative to the importer when it exists, and to the package `foo`
+    /// otherwise. By default, module requests only resolve to packages, even
+    /// if a file with the same name exists next to the importer.
     pub prefer_relative: bool,
     /// The extensions that should be added to a request when resolving.
     pub extensions: Vec<String>,
+    /// The extensions that are added to requests into packages instead of
+    /// [ResolveOptions::extensions], e.g. for `pkg/sub` or a main field
+    /// without an extension. Relative requests, including those from modules
+    /// inside of packages, still use the extensions. Targets of exports fields
+    /// are tried as they are first, so a target with an extension which isn't
+    /// listed still resolves.
+    pub package_extensions: Option<Vec<String>>,
+    /// Replaces the extension of relative requests with alternatives which are
+    /// tried in order, e.g. `.js` with `.ts`, `.tsx` and `.js` for TypeScript
+    /// projects which import `./x.js` to reference `./x.ts`. The first
+    /// alternative which exists as a file wins, so `./x.ts` takes precedence
+    /// over an existing `./x.js` in that example. When none of them exist, the
+    /// request is resolved as usual.
+    pub extension_alias: IndexMap<String, Vec<String>>,
     /// The locations where to resolve modules.
     pub modules: Vec<ResolveModules>,
+    /// Stops looking for packages in the ancestor directories of
+    /// [ResolveModules::Nested] at this directory, so packages installed
+    /// outside of it, e.g. above a workspace root, are not found. The module
+    /// directories of the boundary itself are still used.
+    pub boundary: Option<Vc<FileSystemPath>>,
     /// How to resolve packages.
     pub into_package: Vec<ResolveIntoPackage>,
     /// How to resolve in packages.
     pub in_package: Vec<ResolveInPackage>,
-    /// The default files to resolve in a folder.
+    /// The default files to resolve in a folder. They are only used when none
+    /// of the [ResolveIntoPackage::MainField]s or
+    /// [ResolveIntoPackage::MainFields] of a package.json in the folder
+    /// resolves, so a folder can pick its own entry even when it isn't a
+    /// package in a module directory.
     pub default_files: Vec<String>,
+    /// When set, requests which can't be resolved with the exports or imports
+    /// field of a package emit an issue listing the available subpaths and
+    /// the conditions which were tried.
+    pub exports_field_diagnostics: bool,
+    /// When set, results resolved with the exports or imports field of a
+    /// package carry an [ExportsFieldTrace](super::ExportsFieldTrace) with the
+    /// conditions which were matched and the target which was chosen.
+    pub exports_field_trace: bool,
+    /// Like Node.js, stops looking for a package once a package with an
+    /// exports field has been found, even if it doesn't export the requested
+    /// subpath. By default, packages with the same name in other module
+    /// directories are tried next. The main fields of a package with an
+    /// exports field are never used for a bare request either way.
+    pub strict_exports: bool,
+    /// Keeps the paths of symlinks in resolved paths, like Node.js's
+    /// `--preserve-symlinks`. By default, symlinks are resolved to their real
+    /// path, so a package which is reachable through multiple symlinks, e.g.
+    /// in a pnpm store, resolves to a single module. When they are preserved,
+    /// each symlinked path is a separate module.
+    pub preserve_symlinks: bool,
+    /// Requires static ES module imports of JSON modules to have a
+    /// `type: "json"` import attribute, like browsers and Node.js do. Imports
+    /// without one emit an issue when the resolve error is handled. Imports
+    /// with the attribute use a JSON reference type instead, and `require()`
+    /// calls don't need an attribute, so neither is checked. Dynamic
+    /// `import()` calls aren't checked either.
+    pub strict_json_imports: bool,
     /// An import map to use before resolving a request.
     pub import_map: Option<Vc<ImportMap>>,
     /// An import map to use when a request is otherwise unresolveable.
@@ -436,8 +566,113 @@ pub struct ResolveOptions {
     pub placeholder_for_future_extensions: (),
 }
 
+/// Overrides for some fields of [ResolveOptions], see [ResolveOptions::merge].
+/// Fields which are `None` keep the value of the base options.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct ResolveOptionsPatch {
+    pub fully_specified: Option<bool>,
+    pub prefer_relative: Option<bool>,
+    pub extensions: Option<Vec<String>>,
+    pub package_extensions: Option<Vec<String>>,
+    pub extension_alias: Option<IndexMap<String, Vec<String>>>,
+    pub modules: Option<Vec<ResolveModules>>,
+    pub boundary: Option<Vc<FileSystemPath>>,
+    pub into_package: Option<Vec<ResolveIntoPackage>>,
+    pub in_package: Option<Vec<ResolveInPackage>>,
+    pub default_files: Option<Vec<String>>,
+    pub exports_field_diagnostics: Option<bool>,
+    pub exports_field_trace: Option<bool>,
+    pub strict_exports: Option<bool>,
+    pub preserve_symlinks: Option<bool>,
+    pub strict_json_imports: Option<bool>,
+    /// Conditions which are layered on top of the conditions of all exports
+    /// and imports fields, see [merge_resolution_conditions].
+    pub conditions: Option<ResolutionConditions>,
+}
+
 #[turbo_tasks::value_impl]
 impl ResolveOptions {
+    /// Returns `base` with the fields which are set in `overrides` replaced.
+    /// Conditions are applied after `into_package` and `in_package`, so they
+    /// also apply to exports and imports fields from the overrides.
+    #[turbo_tasks::function]
+    pub async fn merge(
+        base: Vc<ResolveOptions>,
+        overrides: Vc<ResolveOptionsPatch>,
+    ) -> Result<Vc<Self>> {
+        let mut resolve_options = base.await?.clone_value();
+        let overrides = overrides.await?;
+        if let Some(fully_specified) = overrides.fully_specified {
+            resolve_options.fully_specified = fully_specified;
+        }
+        if let Some(prefer_relative) = overrides.prefer_relative {
+            resolve_options.prefer_relative = prefer_relative;
+        }
+        if let Some(extensions) = &overrides.extensions {
+            resolve_options.extensions = extensions.clone();
+        }
+        if let Some(package_extensions) = &overrides.package_extensions {
+            resolve_options.package_extensions = Some(package_extensions.clone());
+        }
+        if let Some(extension_alias) = &overrides.extension_alias {
+            resolve_options.extension_alias = extension_alias.clone();
+        }
+        if let Some(modules) = &overrides.modules {
+            resolve_options.modules = modules.clone();
+        }
+        if let Some(boundary) = overrides.boundary {
+            resolve_options.boundary = Some(boundary);
+        }
+        if let Some(into_package) = &overrides.into_package {
+            resolve_options.into_package = into_package.clone();
+        }
+        if let Some(in_package) = &overrides.in_package {
+            resolve_options.in_package = in_package.clone();
+        }
+        if let Some(default_files) = &overrides.default_files {
+            resolve_options.default_files = default_files.clone();
+        }
+        if let Some(exports_field_diagnostics) = overrides.exports_field_diagnostics {
+            resolve_options.exports_field_diagnostics = exports_field_diagnostics;
+        }
+        if let Some(exports_field_trace) = overrides.exports_field_trace {
+            resolve_options.exports_field_trace = exports_field_trace;
+        }
+        if let Some(strict_exports) = overrides.strict_exports {
+            resolve_options.strict_exports = strict_exports;
+        }
+        if let Some(preserve_symlinks) = overrides.preserve_symlinks {
+            resolve_options.preserve_symlinks = preserve_symlinks;
+        }
+        if let Some(strict_json_imports) = overrides.strict_json_imports {
+            resolve_options.strict_json_imports = strict_json_imports;
+        }
+        if let Some(overridden_conditions) = &overrides.conditions {
+            let into_package_conditions =
+                resolve_options
+                    .into_package
+                    .iter_mut()
+                    .filter_map(|item| match item {
+                        ResolveIntoPackage::ExportsField { conditions, .. } => Some(conditions),
+                        ResolveIntoPackage::MainField { .. }
+                        | ResolveIntoPackage::MainFields { .. } => None,
+                    });
+            let in_package_conditions =
+                resolve_options
+                    .in_package
+                    .iter_mut()
+                    .filter_map(|item| match item {
+                        ResolveInPackage::ImportsField { conditions, .. } => Some(conditions),
+                        ResolveInPackage::AliasField(_) => None,
+                    });
+            for conditions in into_package_conditions.chain(in_package_conditions) {
+                *conditions = merge_resolution_conditions(conditions, overridden_conditions);
+            }
+        }
+        Ok(resolve_options.cell())
+    }
+
     /// Returns a new [Vc<ResolveOptions>] with its import map extended to
     /// include the given import map.
     #[turbo_tasks::function]
@@ -497,7 +732,9 @@ impl ResolveOptions {
 #[derive(Hash, Clone, Debug)]
 pub struct ResolveModulesOptions {
     pub modules: Vec<ResolveModules>,
+    pub boundary: Option<Vc<FileSystemPath>>,
     pub extensions: Vec<String>,
+    pub preserve_symlinks: bool,
 }
 
 #[turbo_tasks::function]
@@ -507,7 +744,12 @@ pub async fn resolve_modules_options(
     let options = options.await?;
     Ok(ResolveModulesOptions {
         modules: options.modules.clone(),
-        extensions: options.extensions.clone(),
+        boundary: options.boundary,
+        extensions: options
+            .package_extensions
+            .clone()
+            .unwrap_or_else(|| options.extensions.clone()),
+        preserve_symlinks: options.preserve_symlinks,
     }
     .into())
 }
@@ -521,3 +763,67 @@ pub trait ImportMappingReplacement {
         request: Vc<Request>,
     ) -> Vc<ImportMapResult>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_condition_values() {
+        use ConditionValue::*;
+
+        assert_eq!(Set.merge(Set), Set);
+        assert_eq!(Unset.merge(Unset), Unset);
+        assert_eq!(Set.merge(Unset), Unknown);
+        assert_eq!(Unset.merge(Set), Unknown);
+        assert_eq!(Unknown.merge(Set), Unknown);
+        assert_eq!(Unset.merge(Unknown), Unknown);
+        assert_eq!(Negated.merge(Negated), Negated);
+        assert_eq!(Negated.merge(Unset), Negated);
+        assert_eq!(Set.merge(Negated), Negated);
+        assert_eq!(Unknown.merge(Negated), Negated);
+    }
+
+    #[test]
+    fn merge_conditions() {
+        use ConditionValue::*;
+
+        let conditions = |value: Option<ConditionValue>| -> ResolutionConditions {
+            value
+                .map(|value| ("c".to_string(), value))
+                .into_iter()
+                .collect()
+        };
+        let cases = [
+            (Set, Set, Set),
+            (Set, Unset, Set),
+            (Unset, Set, Set),
+            (Unset, Unset, Unset),
+            (Unknown, Unset, Unset),
+            (Unset, Unknown, Unknown),
+            (Set, Unknown, Unknown),
+            (Negated, Set, Negated),
+            (Set, Negated, Negated),
+            (Negated, Unknown, Negated),
+            (Unknown, Negated, Negated),
+        ];
+        for (earlier, later, expected) in cases {
+            assert_eq!(
+                merge_resolution_conditions(&conditions(Some(earlier)), &conditions(Some(later))),
+                conditions(Some(expected)),
+                "{earlier:?} then {later:?}"
+            );
+        }
+        // Conditions which are only in one source keep their value.
+        for value in [Set, Unset, Negated] {
+            assert_eq!(
+                merge_resolution_conditions(&conditions(Some(value)), &conditions(None)),
+                conditions(Some(value))
+            );
+            assert_eq!(
+                merge_resolution_conditions(&conditions(None), &conditions(Some(value))),
+                conditions(Some(value))
+            );
+        }
+    }
+}
@@ -17,23 +17,29 @@ pub enum Request {
         path: Pattern,
         query: Vc<String>,
         force_in_lookup_dir: bool,
+        fragment: Vc<String>,
     },
     Module {
         module: String,
         path: Pattern,
         query: Vc<String>,
+        fragment: Vc<String>,
     },
     ServerRelative {
         path: Pattern,
         query: Vc<String>,
+        fragment: Vc<String>,
     },
     Windows {
         path: Pattern,
         query: Vc<String>,
+        fragment: Vc<String>,
     },
     Empty,
     PackageInternal {
         path: Pattern,
+        query: Vc<String>,
+        fragment: Vc<String>,
     },
     Uri {
         protocol: String,
@@ -48,14 +54,31 @@ pub enum Request {
     },
 }
 
-fn split_off_query(raw: String) -> (Pattern, Vc<String>) {
-    let Some((raw, query)) = raw.split_once('?') else {
-        return (Pattern::Constant(raw), Vc::<String>::default());
+/// Splits the query and the fragment off a request, e.g. `./x.svg?raw#icon`
+/// into `./x.svg`, `?raw` and `#icon`. Like in a URL, the fragment is
+/// everything after the first `#`, even a `?`. The first `skip` bytes are
+/// never split off, e.g. the `#` of an imports field specifier.
+fn split_off_query_fragment(raw: String, skip: usize) -> (Pattern, Vc<String>, Vc<String>) {
+    let (raw, fragment) = match raw[skip..].find('#') {
+        Some(index) => {
+            let (raw, fragment) = raw.split_at(skip + index);
+            (raw, Vc::cell(fragment.to_string()))
+        }
+        None => (raw.as_str(), Vc::<String>::default()),
+    };
+    let Some(index) = raw[skip..].find('?') else {
+        return (
+            Pattern::Constant(raw.to_string()),
+            Vc::<String>::default(),
+            fragment,
+        );
     };
 
+    let (raw, query) = raw.split_at(skip + index);
     (
         Pattern::Constant(raw.to_string()),
-        Vc::cell(format!("?{}", query)),
+        Vc::cell(query.to_string()),
+        fragment,
     )
 }
 
@@ -107,33 +130,51 @@ impl Request {
                 if r.is_empty() {
                     Request::Empty
                 } else if r.starts_with('/') {
-                    let (path, query) = split_off_query(r);
+                    let (path, query, fragment) = split_off_query_fragment(r, 0);
 
-                    Request::ServerRelative { path, query }
+                    Request::ServerRelative {
+                        path,
+                        query,
+                        fragment,
+                    }
                 } else if r.starts_with('#') {
+                    // Only a leading `#` makes this an imports field specifier.
+                    // Another `#` starts a fragment, e.g. in
+                    // `#internal.svg#icon`.
+                    let (path, query, fragment) = split_off_query_fragment(r, 1);
+
                     Request::PackageInternal {
-                        path: Pattern::Constant(r),
+                        path,
+                        query,
+                        fragment,
                     }
                 } else if r.starts_with("./") || r.starts_with("../") || r == "." || r == ".." {
-                    let (path, query) = split_off_query(r);
+                    let (path, query, fragment) = split_off_query_fragment(r, 0);
 
                     Request::Relative {
                         path,
                         force_in_lookup_dir: false,
                         query,
+                        fragment,
                     }
                 } else {
                     lazy_static! {
                         static ref WINDOWS_PATH: Regex = Regex::new(r"^[A-Za-z]:\\|\\\\").unwrap();
                         static ref URI_PATH: Regex = Regex::new(r"^([^/\\]+:)(.+)$").unwrap();
+                        // The query or fragment of a bare request like `pkg?raw` is
+                        // not part of the module name.
                         static ref MODULE_PATH: Regex =
-                            Regex::new(r"^((?:@[^/]+/)?[^/]+)(.*)$").unwrap();
+                            Regex::new(r"^((?:@[^/]+/)?[^/?#]+)(.*)$").unwrap();
                     }
 
                     if WINDOWS_PATH.is_match(&r) {
-                        let (path, query) = split_off_query(r);
+                        let (path, query, fragment) = split_off_query_fragment(r, 0);
 
-                        return Request::Windows { path, query };
+                        return Request::Windows {
+                            path,
+                            query,
+                            fragment,
+                        };
                     }
 
                     if let Some(caps) = URI_PATH.captures(&r) {
@@ -150,12 +191,14 @@ impl Request {
                         .captures(&r)
                         .and_then(|caps| caps.get(1).zip(caps.get(2)))
                     {
-                        let (path, query) = split_off_query(path.as_str().to_string());
+                        let (path, query, fragment) =
+                            split_off_query_fragment(path.as_str().to_string(), 0);
 
                         return Request::Module {
                             module: module.as_str().to_string(),
                             path,
                             query,
+                            fragment,
                         };
                     }
 
@@ -187,7 +230,7 @@ impl Request {
                         Request::Empty => {
                             result = Request::parse_ref(Pattern::Concatenation(iter.collect()))
                         }
-                        Request::PackageInternal { path } => {
+                        Request::PackageInternal { path, .. } => {
                             path.extend(iter);
                         }
                         Request::Uri { .. } => {
@@ -246,6 +289,7 @@ impl Request {
             path: request.into_value(),
             force_in_lookup_dir,
             query,
+            fragment: Vc::<String>::default(),
         })
     }
 
@@ -255,6 +299,7 @@ impl Request {
             module,
             path: path.into_value(),
             query,
+            fragment: Vc::<String>::default(),
         })
     }
 
@@ -269,17 +314,13 @@ impl Request {
             | Request::Relative { .. }
             | Request::Uri { .. }
             | Request::Dynamic => self,
-            Request::Module {
-                module,
-                path,
-                query: _,
-            } => {
+            Request::Module { module, path, .. } => {
                 let mut pat = Pattern::Constant(format!("./{module}"));
                 pat.push(path.clone());
                 // TODO add query
                 Self::parse(Value::new(pat))
             }
-            Request::PackageInternal { path } => {
+            Request::PackageInternal { path, .. } => {
                 let mut pat = Pattern::Constant("./".to_string());
                 pat.push(path.clone());
                 Self::parse(Value::new(pat))
@@ -313,34 +354,57 @@ impl Request {
                 path,
                 query: _,
                 force_in_lookup_dir,
+                fragment,
             } => Request::Relative {
                 path: path.clone(),
                 query,
                 force_in_lookup_dir: *force_in_lookup_dir,
+                fragment: *fragment,
             }
             .cell(),
             Request::Module {
                 module,
                 path,
                 query: _,
+                fragment,
             } => Request::Module {
                 module: module.clone(),
                 path: path.clone(),
                 query,
+                fragment: *fragment,
             }
             .cell(),
-            Request::ServerRelative { path, query: _ } => Request::ServerRelative {
+            Request::ServerRelative {
+                path,
+                query: _,
+                fragment,
+            } => Request::ServerRelative {
                 path: path.clone(),
                 query,
+                fragment: *fragment,
             }
             .cell(),
-            Request::Windows { path, query: _ } => Request::Windows {
+            Request::Windows {
+                path,
+                query: _,
+                fragment,
+            } => Request::Windows {
                 path: path.clone(),
                 query,
+                fragment: *fragment,
+            }
+            .cell(),
+            Request::PackageInternal {
+                path,
+                query: _,
+                fragment,
+            } => Request::PackageInternal {
+                path: path.clone(),
+                query,
+                fragment: *fragment,
             }
             .cell(),
             Request::Empty => self,
-            Request::PackageInternal { .. } => self,
             Request::Uri { .. } => self,
             Request::Unknown { .. } => self,
             Request::Dynamic => self,
@@ -371,43 +435,76 @@ impl Request {
                 path,
                 query,
                 force_in_lookup_dir,
+                fragment,
             } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
-                Self::relative(Value::new(pat), *query, *force_in_lookup_dir)
+                Self::Relative {
+                    path: pat,
+                    query: *query,
+                    force_in_lookup_dir: *force_in_lookup_dir,
+                    fragment: *fragment,
+                }
+                .cell()
             }
             Request::Module {
                 module,
                 path,
                 query,
+                fragment,
             } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
-                Self::module(module.clone(), Value::new(pat), *query)
+                Self::Module {
+                    module: module.clone(),
+                    path: pat,
+                    query: *query,
+                    fragment: *fragment,
+                }
+                .cell()
             }
-            Request::ServerRelative { path, query } => {
+            Request::ServerRelative {
+                path,
+                query,
+                fragment,
+            } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
                 Self::ServerRelative {
                     path: pat,
                     query: *query,
+                    fragment: *fragment,
                 }
                 .cell()
             }
-            Request::Windows { path, query } => {
+            Request::Windows {
+                path,
+                query,
+                fragment,
+            } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
                 Self::Windows {
                     path: pat,
                     query: *query,
+                    fragment: *fragment,
                 }
                 .cell()
             }
             Request::Empty => Self::parse(Value::new(suffix.into())),
-            Request::PackageInternal { path } => {
+            Request::PackageInternal {
+                path,
+                query,
+                fragment,
+            } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
-                Self::PackageInternal { path: pat }.cell()
+                Self::PackageInternal {
+                    path: pat,
+                    query: *query,
+                    fragment: *fragment,
+                }
+                .cell()
             }
             Request::Uri {
                 protocol,
@@ -445,7 +542,7 @@ impl Request {
             Request::ServerRelative { query, .. } => *query,
             Request::Windows { query, .. } => *query,
             Request::Empty => Vc::<String>::default(),
-            Request::PackageInternal { .. } => Vc::<String>::default(),
+            Request::PackageInternal { query, .. } => *query,
             Request::Uri { .. } => Vc::<String>::default(),
             Request::Unknown { .. } => Vc::<String>::default(),
             Request::Dynamic => Vc::<String>::default(),
@@ -453,6 +550,96 @@ impl Request {
             Request::Alternatives { .. } => Vc::<String>::default(),
         }
     }
+
+    /// Returns the fragment of the request including the leading `#`, e.g.
+    /// `#icon` for `./x.svg#icon`, or an empty string if there is none.
+    #[turbo_tasks::function]
+    pub fn fragment(&self) -> Vc<String> {
+        match self {
+            Request::Relative { fragment, .. }
+            | Request::Module { fragment, .. }
+            | Request::ServerRelative { fragment, .. }
+            | Request::Windows { fragment, .. }
+            | Request::PackageInternal { fragment, .. } => *fragment,
+            Request::Raw { .. }
+            | Request::Empty
+            | Request::Uri { .. }
+            | Request::Unknown { .. }
+            | Request::Dynamic
+            | Request::Alternatives { .. } => Vc::<String>::default(),
+        }
+    }
+
+    /// Returns the request with its fragment appended to the path again, for
+    /// files whose names contain a `#`, e.g. `./icon#dark.svg`. A `?` in the
+    /// fragment starts the query then, e.g. in `./icon#dark.svg?raw`. Returns
+    /// the request as it is if it has no fragment.
+    #[turbo_tasks::function]
+    pub async fn with_fragment_in_path(self: Vc<Self>) -> Result<Vc<Self>> {
+        let fragment = self.fragment().await?;
+        if fragment.is_empty() {
+            return Ok(self);
+        }
+        let has_query = !self.query().await?.is_empty();
+        let (fragment_path, fragment_query) = match fragment.split_once('?') {
+            Some((fragment_path, fragment_query)) if !has_query => {
+                (fragment_path, Some(Vc::cell(format!("?{fragment_query}"))))
+            }
+            _ => (fragment.as_str(), None),
+        };
+        let path_with_fragment = |path: &Pattern| {
+            let mut pat =
+                Pattern::concat([path.clone(), Pattern::Constant(fragment_path.to_string())]);
+            pat.normalize();
+            pat
+        };
+        Ok(match &*self.await? {
+            Request::Relative {
+                path,
+                query,
+                force_in_lookup_dir,
+                ..
+            } => Request::Relative {
+                path: path_with_fragment(path),
+                query: fragment_query.unwrap_or(*query),
+                force_in_lookup_dir: *force_in_lookup_dir,
+                fragment: Vc::<String>::default(),
+            },
+            Request::Module {
+                module,
+                path,
+                query,
+                ..
+            } => Request::Module {
+                module: module.clone(),
+                path: path_with_fragment(path),
+                query: fragment_query.unwrap_or(*query),
+                fragment: Vc::<String>::default(),
+            },
+            Request::ServerRelative { path, query, .. } => Request::ServerRelative {
+                path: path_with_fragment(path),
+                query: fragment_query.unwrap_or(*query),
+                fragment: Vc::<String>::default(),
+            },
+            Request::Windows { path, query, .. } => Request::Windows {
+                path: path_with_fragment(path),
+                query: fragment_query.unwrap_or(*query),
+                fragment: Vc::<String>::default(),
+            },
+            Request::PackageInternal { path, query, .. } => Request::PackageInternal {
+                path: path_with_fragment(path),
+                query: fragment_query.unwrap_or(*query),
+                fragment: Vc::<String>::default(),
+            },
+            Request::Raw { .. }
+            | Request::Empty
+            | Request::Uri { .. }
+            | Request::Unknown { .. }
+            | Request::Dynamic
+            | Request::Alternatives { .. } => return Ok(self),
+        }
+        .cell())
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -492,7 +679,7 @@ impl ValueToString for Request {
             Request::ServerRelative { path, .. } => format!("server relative {path}"),
             Request::Windows { path, .. } => format!("windows {path}"),
             Request::Empty => "empty".to_string(),
-            Request::PackageInternal { path } => format!("package internal {path}"),
+            Request::PackageInternal { path, .. } => format!("package internal {path}"),
             Request::Uri {
                 protocol,
                 remainder,
@@ -30,6 +30,10 @@ impl Display for ExportImport {
     }
 }
 
+/// A target of an "exports"/"imports" field, together with the conditions
+/// which need to be set (`true`) or unset (`false`) for it to apply.
+pub type SubpathResult<'a> = (&'a str, Vec<(&'a str, bool)>);
+
 /// The result an "exports"/"imports" field describes. Can represent multiple
 /// alternatives, conditional result, ignored result (null mapping) and a plain
 /// result.
@@ -46,6 +50,7 @@ pub enum SubpathValue {
     /// ESM import syntax, the `import` condition allows you to remap to a
     /// file that uses ESM syntax.
     /// Node defines several conditions in https://nodejs.org/api/packages.html#conditional-exports
+    /// Conditions are case-sensitive, so `Import` doesn't match `import`.
     /// TODO: Should this use an enum of predefined keys?
     Conditional(Vec<(String, SubpathValue)>),
 
@@ -87,28 +92,52 @@ impl SubpathValue {
 
     /// Walks the [SubpathValue] and adds results to the `target` vector. It
     /// uses the `conditions` to skip or enter conditional results.
+    /// Conditional results are visited in the order they are defined in, so
+    /// when multiple conditions match, the first one in the package.json wins.
     /// The state of conditions is stored within `condition_overrides`, which is
     /// also exposed to the consumer.
+    ///
+    /// Results are added to the last group of `target`. The entries of an
+    /// array after the first one that has results start new groups, which are
+    /// fallbacks to be used in order when none of the targets of the previous
+    /// groups exist. A `null` entry stops the fallbacks.
     pub fn add_results<'a>(
         &'a self,
         conditions: &BTreeMap<String, ConditionValue>,
         unspecified_condition: &ConditionValue,
         condition_overrides: &mut HashMap<&'a str, ConditionValue>,
-        target: &mut Vec<(&'a str, Vec<(&'a str, bool)>)>,
+        target: &mut Vec<Vec<SubpathResult<'a>>>,
     ) -> bool {
         match self {
             SubpathValue::Alternatives(list) => {
+                let count = |target: &Vec<Vec<SubpathResult>>| -> usize {
+                    target.iter().map(Vec::len).sum()
+                };
+                let mut found = false;
                 for value in list {
-                    if value.add_results(
+                    if found {
+                        target.push(Vec::new());
+                    }
+                    let before = count(target);
+                    let matched = value.add_results(
                         conditions,
                         unspecified_condition,
                         condition_overrides,
                         target,
-                    ) {
+                    );
+                    if count(target) > before {
+                        found = true;
+                        continue;
+                    }
+                    if found {
+                        target.pop();
+                    }
+                    // The entry matched without results, so it's excluded.
+                    if matched {
                         return true;
                     }
                 }
-                false
+                found
             }
             SubpathValue::Conditional(list) => {
                 for (condition, value) in list {
@@ -131,7 +160,7 @@ impl SubpathValue {
                                 return true;
                             }
                         }
-                        ConditionValue::Unset => {}
+                        ConditionValue::Unset | ConditionValue::Negated => {}
                         ConditionValue::Unknown => {
                             condition_overrides.insert(condition, ConditionValue::Set);
                             if value.add_results(
@@ -150,13 +179,17 @@ impl SubpathValue {
                 false
             }
             SubpathValue::Result(r) => {
+                if target.is_empty() {
+                    target.push(Vec::new());
+                }
+                let target = target.last_mut().unwrap();
                 target.push((
                     r,
                     condition_overrides
                         .iter()
                         .filter_map(|(k, v)| match v {
                             ConditionValue::Set => Some((*k, true)),
-                            ConditionValue::Unset => Some((*k, false)),
+                            ConditionValue::Unset | ConditionValue::Negated => Some((*k, false)),
                             ConditionValue::Unknown => None,
                         })
                         .collect(),
@@ -167,6 +200,50 @@ impl SubpathValue {
         }
     }
 
+    /// Walks the [SubpathValue] like [SubpathValue::add_results], but only
+    /// follows the first matching path. The conditions entered on the way are
+    /// pushed to `matched` in order. Unknown conditions are entered as if they
+    /// were set.
+    ///
+    /// Returns `None` when nothing matches, `Some(None)` when the path ends in
+    /// a `null` target and `Some(Some(target))` otherwise.
+    pub fn trace<'a>(
+        &'a self,
+        conditions: &BTreeMap<String, ConditionValue>,
+        unspecified_condition: &ConditionValue,
+        matched: &mut Vec<&'a str>,
+    ) -> Option<Option<&'a str>> {
+        match self {
+            SubpathValue::Alternatives(list) => list
+                .iter()
+                .find_map(|value| value.trace(conditions, unspecified_condition, matched)),
+            SubpathValue::Conditional(list) => {
+                for (condition, value) in list {
+                    let condition_value = if condition == "default" {
+                        &ConditionValue::Set
+                    } else {
+                        conditions.get(condition).unwrap_or(unspecified_condition)
+                    };
+                    match condition_value {
+                        ConditionValue::Set | ConditionValue::Unknown => {
+                            matched.push(condition);
+                            if let Some(target) =
+                                value.trace(conditions, unspecified_condition, matched)
+                            {
+                                return Some(target);
+                            }
+                            matched.pop();
+                        }
+                        ConditionValue::Unset | ConditionValue::Negated => {}
+                    }
+                }
+                None
+            }
+            SubpathValue::Result(r) => Some(Some(r)),
+            SubpathValue::Excluded => Some(None),
+        }
+    }
+
     fn try_new(value: &Value, ty: ExportImport) -> Result<Self> {
         match value {
             Value::Null => Ok(SubpathValue::Excluded),
@@ -434,3 +511,134 @@ impl<'a> IntoIterator for &'a ResolveAliasMap {
         (&self.0).into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unknown_conditions_add_all_results() {
+        let exports = ExportsField::try_from(&json!({
+            "node": "./node.js",
+            "browser": "./browser.js",
+        }))
+        .unwrap();
+        let value = exports.lookup(".").next().unwrap().try_into_self().unwrap();
+        let conditions = [
+            ("node".to_string(), ConditionValue::Unknown),
+            ("browser".to_string(), ConditionValue::Unknown),
+        ]
+        .into();
+
+        let mut results = Vec::new();
+        value.add_results(
+            &conditions,
+            &ConditionValue::Unset,
+            &mut HashMap::new(),
+            &mut results,
+        );
+        for (_, conditions) in results.iter_mut().flatten() {
+            conditions.sort();
+        }
+
+        assert_eq!(
+            results,
+            vec![vec![
+                ("./node.js", vec![("node", true)]),
+                ("./browser.js", vec![("browser", true), ("node", false)]),
+            ]]
+        );
+    }
+
+    /// Returns the targets of the `.` export, with fallbacks in separate
+    /// groups.
+    fn resolve_with_conditions(exports: &ExportsField, conditions: &[&str]) -> Vec<Vec<String>> {
+        let conditions = conditions
+            .iter()
+            .map(|condition| (condition.to_string(), ConditionValue::Set))
+            .collect();
+        let value = exports.lookup(".").next().unwrap().try_into_self().unwrap();
+        let mut results = Vec::new();
+        value.add_results(
+            &conditions,
+            &ConditionValue::Unset,
+            &mut HashMap::new(),
+            &mut results,
+        );
+        results
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|(result, _)| result.to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn condition_priority_follows_package_order() {
+        let import_first = ExportsField::try_from(&json!({
+            "import": "./a.js",
+            "require": "./b.js",
+        }))
+        .unwrap();
+        let require_first = ExportsField::try_from(&json!({
+            "require": "./b.js",
+            "import": "./a.js",
+        }))
+        .unwrap();
+
+        // The order of the resolver's conditions doesn't matter.
+        for conditions in [["import", "require"], ["require", "import"]] {
+            assert_eq!(
+                resolve_with_conditions(&import_first, &conditions),
+                vec![vec!["./a.js"]]
+            );
+            assert_eq!(
+                resolve_with_conditions(&require_first, &conditions),
+                vec![vec!["./b.js"]]
+            );
+        }
+    }
+
+    #[test]
+    fn array_fallbacks() {
+        let exports = ExportsField::try_from(&json!({
+            ".": ["./a.js", { "import": "./b.mjs", "default": "./b.js" }, "./c.js"],
+        }))
+        .unwrap();
+        assert_eq!(
+            resolve_with_conditions(&exports, &["import"]),
+            vec![vec!["./a.js"], vec!["./b.mjs"], vec!["./c.js"]]
+        );
+
+        // Entries without matching conditions are skipped, and nested arrays
+        // are flattened.
+        let exports = ExportsField::try_from(&json!({
+            ".": [{ "browser": "./browser.js" }, ["./a.js", "./b.js"]],
+        }))
+        .unwrap();
+        assert_eq!(
+            resolve_with_conditions(&exports, &["import"]),
+            vec![vec!["./a.js"], vec!["./b.js"]]
+        );
+    }
+
+    #[test]
+    fn array_null_stops_fallbacks() {
+        let exports = ExportsField::try_from(&json!({
+            ".": ["./a.js", null, "./b.js"],
+        }))
+        .unwrap();
+        assert_eq!(resolve_with_conditions(&exports, &[]), vec![vec!["./a.js"]]);
+
+        let exports = ExportsField::try_from(&json!({
+            ".": [null, "./a.js"],
+        }))
+        .unwrap();
+        assert!(resolve_with_conditions(&exports, &[]).is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+#![cfg(test)]
+
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{FileSystem, VirtualFileSystem};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    issue::IssueDescriptionExt,
+    reference_type::{CssReferenceSubType, ImportContext, ImportLayer, ReferenceType},
+    register,
+};
+
+register!();
+
+#[tokio::test]
+async fn at_import_includes() {
+    run! {
+        register();
+
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let print = empty.add_attributes(ImportLayer::None, Some("print".to_string()), None);
+        let screen = empty.add_attributes(ImportLayer::None, Some("screen".to_string()), None);
+        let any = ReferenceType::Css(CssReferenceSubType::at_import(empty).await?);
+        let print = ReferenceType::Css(CssReferenceSubType::at_import(print).await?);
+        let screen = ReferenceType::Css(CssReferenceSubType::at_import(screen).await?);
+
+        assert!(!print.includes(&screen));
+        assert!(!screen.includes(&print));
+        assert!(print.includes(&print));
+        assert!(any.includes(&print));
+        assert!(print.includes(&any));
+        assert!(!print.includes(&ReferenceType::Css(CssReferenceSubType::Compose(None))));
+    }
+}
+
+#[tokio::test]
+async fn at_import_same_conditions() {
+    run! {
+        register();
+
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let via_attributes = CssReferenceSubType::at_import(
+            empty.add_attributes(ImportLayer::None, Some("print".to_string()), None),
+        )
+        .await?;
+        let via_new = CssReferenceSubType::at_import(ImportContext::new(
+            vec![],
+            vec!["print".to_string()],
+            vec![],
+        ))
+        .await?;
+
+        assert_eq!(via_attributes, via_new);
+        assert!(ReferenceType::Css(via_attributes).includes(&ReferenceType::Css(via_new)));
+    }
+}
+
+#[tokio::test]
+async fn from_str_conditions() {
+    run! {
+        register();
+
+        let from_strs = ImportContext::from_conditions(
+            vec![ImportLayer::named("base")],
+            ["print"],
+            ["display: grid"],
+        );
+        let from_strings = ImportContext::new(
+            vec![ImportLayer::Named("base".to_string())],
+            vec!["print".to_string()],
+            vec!["display: grid".to_string()],
+        );
+
+        assert_eq!(*from_strs.await?, *from_strings.await?);
+        assert_eq!(
+            CssReferenceSubType::at_import(from_strs).await?,
+            CssReferenceSubType::at_import(from_strings).await?
+        );
+    }
+}
+
+#[tokio::test]
+async fn at_import_layers() {
+    run! {
+        register();
+
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let none = empty.add_attributes(ImportLayer::None, None, None);
+        let anonymous = empty.add_attributes(ImportLayer::Anonymous, None, None);
+        let named = empty.add_attributes(ImportLayer::Named("a".to_string()), None, None);
+
+        assert_eq!(none.resolve().await?, empty.resolve().await?);
+        assert_eq!(anonymous.await?.layer_blocks(), vec![ImportLayer::Anonymous]);
+        assert_eq!(
+            named.await?.layer_blocks(),
+            vec![ImportLayer::Named("a".to_string())]
+        );
+
+        let anonymous = ReferenceType::Css(CssReferenceSubType::at_import(anonymous).await?);
+        let named = ReferenceType::Css(CssReferenceSubType::at_import(named).await?);
+        assert!(!anonymous.includes(&named));
+        assert!(!named.includes(&anonymous));
+    }
+}
+
+#[tokio::test]
+async fn at_import_layer_only() {
+    run! {
+        register();
+
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let layer = empty.add_attributes(ImportLayer::Named("a".to_string()), None, None);
+        let sub_type = CssReferenceSubType::at_import(layer).await?;
+        assert_eq!(
+            sub_type,
+            CssReferenceSubType::AtImportLayerOnly(vec![ImportLayer::Named("a".to_string())])
+        );
+        // Converting back results in the same context.
+        assert_eq!(sub_type.import_context().unwrap().resolve().await?, layer.resolve().await?);
+
+        // With media queries, the full context is kept.
+        let layer_print = layer.add_attributes(ImportLayer::None, Some("print".to_string()), None);
+        let sub_type = CssReferenceSubType::at_import(layer_print).await?;
+        assert_eq!(
+            sub_type,
+            CssReferenceSubType::AtImport(Some(layer_print.resolve().await?))
+        );
+
+        let layer = ReferenceType::Css(CssReferenceSubType::at_import(layer).await?);
+        let layer_print = ReferenceType::Css(sub_type);
+        let any = ReferenceType::Css(CssReferenceSubType::at_import(empty).await?);
+        assert!(any.includes(&layer));
+        assert!(layer.includes(&any));
+        assert!(!layer.includes(&layer_print));
+        assert!(!layer_print.includes(&layer));
+    }
+}
+
+#[tokio::test]
+async fn cache_key() {
+    run! {
+        register();
+
+        let key = |layers: &[&str], media: &[&str], supports: &[&str]| {
+            let strings = |list: &[&str]| -> Vec<String> {
+                list.iter().map(|s| s.to_string()).collect()
+            };
+            ImportContext::new(
+                layers.iter().map(|layer| ImportLayer::Named(layer.to_string())).collect(),
+                strings(media),
+                strings(supports),
+            )
+            .cache_key()
+        };
+
+        let print = key(&["a"], &["print"], &[]).await?;
+        assert_eq!(*print, *key(&["a"], &["print"], &[]).await?);
+        let empty = key(&[], &[], &[]).await?;
+        assert_eq!(*empty, *key(&[], &[], &[]).await?);
+
+        let others = [
+            empty,
+            key(&["a"], &["screen"], &[]).await?,
+            key(&["a"], &[], &["print"]).await?,
+            key(&[], &["print"], &[]).await?,
+            key(&["a"], &["print", "screen"], &[]).await?,
+            key(&["a"], &["screen", "print"], &[]).await?,
+            key(&["a", "b"], &["print"], &[]).await?,
+            key(&["b", "a"], &["print"], &[]).await?,
+        ];
+        for (i, other) in others.iter().enumerate() {
+            assert_ne!(*print, **other);
+            for previous in &others[..i] {
+                assert_ne!(**previous, **other);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn without_projections() {
+    run! {
+        register();
+
+        let context = ImportContext::new(
+            vec![ImportLayer::Named("a".to_string())],
+            vec!["print".to_string()],
+            vec!["display: grid".to_string()],
+        );
+
+        let without_layer = context.without_layer().await?;
+        assert!(without_layer.layers.is_empty());
+        assert_eq!(without_layer.media, ["print"]);
+        assert_eq!(without_layer.supports, ["display: grid"]);
+
+        let without_media = context.without_media().await?;
+        assert_eq!(without_media.layers, [ImportLayer::Named("a".to_string())]);
+        assert!(without_media.media.is_empty());
+        assert_eq!(without_media.supports, ["display: grid"]);
+
+        let without_supports = context.without_supports().await?;
+        assert_eq!(without_supports.layers, [ImportLayer::Named("a".to_string())]);
+        assert_eq!(without_supports.media, ["print"]);
+        assert!(without_supports.supports.is_empty());
+
+        // Clearing everything results in the empty context, in any order.
+        let empty = ImportContext::new(vec![], vec![], vec![]).resolve().await?;
+        let cleared = context.without_layer().without_media().without_supports();
+        assert_eq!(*cleared.await?, ImportContext::default());
+        assert_eq!(cleared.resolve().await?, empty);
+        assert_eq!(
+            context
+                .without_supports()
+                .without_layer()
+                .without_media()
+                .resolve()
+                .await?,
+            empty
+        );
+        assert_eq!(empty.without_layer().resolve().await?, empty);
+    }
+}
+
+#[tokio::test]
+async fn new_removes_duplicates() {
+    run! {
+        register();
+
+        let context = ImportContext::new(
+            vec![ImportLayer::Named("a".to_string()), ImportLayer::Named("a".to_string())],
+            vec!["screen".to_string(), "print".to_string(), "screen".to_string()],
+            vec!["display: grid".to_string(), "display: grid".to_string()],
+        );
+        let canonical = ImportContext::new(
+            vec![ImportLayer::Named("a".to_string()), ImportLayer::Named("a".to_string())],
+            vec!["screen".to_string(), "print".to_string()],
+            vec!["display: grid".to_string()],
+        );
+
+        // The first occurrence is kept. Layers are nested, so they can repeat.
+        let value = context.await?;
+        assert_eq!(value.media, ["screen", "print"]);
+        assert_eq!(value.supports, ["display: grid"]);
+        assert_eq!(value.layers.len(), 2);
+        assert_eq!(context.resolve().await?, canonical.resolve().await?);
+    }
+}
+
+#[tokio::test]
+async fn import_cycles() {
+    run! {
+        register();
+
+        let root = VirtualFileSystem::new().root();
+        let [a, b, c, d] =
+            ["a.css", "b.css", "c.css", "d.css"].map(|name| root.join(name.to_string()));
+        let layer = |name: &str| ImportLayer::Named(name.to_string());
+        // Returns the context and the number of cycles which were reported.
+        let accumulate = |context: Vc<ImportContext>| async move {
+            let resolved = context.resolve_strongly_consistent().await?;
+            // The issues are collected from the task, not from the cell it returns.
+            let issues = context.peek_issues_with_path().await?;
+            anyhow::Ok((resolved, issues.len()))
+        };
+
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let in_b = empty.add_attributes_in_chain(vec![a], b, layer("b"), None, None);
+        let in_c = empty.add_attributes_in_chain(vec![a], c, layer("c"), None, None);
+
+        // `a` imports `b` and `c`, which both import `d`.
+        let (via_b, cycles) =
+            accumulate(in_b.add_attributes_in_chain(vec![a, b], d, layer("d"), None, None)).await?;
+        assert_eq!(cycles, 0);
+        assert_eq!(via_b.await?.layers, [layer("b"), layer("d")]);
+        let (via_c, cycles) =
+            accumulate(in_c.add_attributes_in_chain(vec![a, c], d, layer("d"), None, None)).await?;
+        assert_eq!(cycles, 0);
+        assert_eq!(via_c.await?.layers, [layer("c"), layer("d")]);
+
+        // `b` imports `a` again, so its layer isn't added.
+        let (cycle, cycles) =
+            accumulate(in_b.add_attributes_in_chain(vec![a, b], a, layer("a"), None, None)).await?;
+        assert_eq!(cycles, 1);
+        assert_eq!(cycle, in_b.resolve().await?);
+
+        // `a` imports itself.
+        let (cycle, cycles) =
+            accumulate(empty.add_attributes_in_chain(vec![a], a, layer("a"), None, None)).await?;
+        assert_eq!(cycles, 1);
+        assert_eq!(cycle, empty.resolve().await?);
+    }
+}
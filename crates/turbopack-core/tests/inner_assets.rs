@@ -0,0 +1,173 @@
+#![cfg(test)]
+
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{FileContent, FileSystem, VirtualFileSystem};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    asset::AssetContent,
+    module::Module,
+    raw_module::RawModule,
+    reference_type::{InnerAssets, ReferenceType},
+    register,
+    virtual_source::VirtualSource,
+};
+
+register!();
+
+/// Creates a module with the given file name on a virtual file system.
+fn module(name: &str) -> Vc<Box<dyn Module>> {
+    let fs: Vc<Box<dyn FileSystem>> = Vc::upcast(VirtualFileSystem::new());
+    let source = VirtualSource::new(
+        fs.root().join(name.to_string()),
+        AssetContent::file(FileContent::NotFound.cell()),
+    );
+    Vc::upcast(RawModule::new(Vc::upcast(source)))
+}
+
+#[tokio::test]
+async fn with_asset() {
+    run! {
+        register();
+
+        let a = module("a.js");
+        let b = module("b.js");
+        let c = module("c.js");
+        let assets = InnerAssets::empty()
+            .with_asset("A".to_string(), a)
+            .with_asset("B".to_string(), b)
+            .with_asset("A".to_string(), c)
+            .await?;
+
+        assert_eq!(assets.keys().collect::<Vec<_>>(), ["A", "B"]);
+        assert_eq!(assets["A"].resolve().await?, c.resolve().await?);
+        assert_eq!(assets["B"].resolve().await?, b.resolve().await?);
+    }
+}
+
+#[tokio::test]
+async fn get_and_contains_key() {
+    run! {
+        register();
+
+        let a = module("a.js");
+        let assets = InnerAssets::empty().with_asset("A".to_string(), a);
+
+        assert_eq!(
+            assets.get("A".to_string()).await?.unwrap().resolve().await?,
+            a.resolve().await?
+        );
+        assert!(assets.get("B".to_string()).await?.is_none());
+        assert!(*assets.contains_key("A".to_string()).await?);
+        assert!(!*assets.contains_key("B".to_string()).await?);
+    }
+}
+
+#[tokio::test]
+async fn len_and_is_empty() {
+    run! {
+        register();
+
+        let empty = InnerAssets::empty();
+        assert_eq!(*empty.len().await?, 0);
+        assert!(*empty.is_empty().await?);
+
+        let assets = empty
+            .with_asset("A".to_string(), module("a.js"))
+            .with_asset("B".to_string(), module("b.js"));
+        assert_eq!(*assets.len().await?, 2);
+        assert!(!*assets.is_empty().await?);
+    }
+}
+
+#[tokio::test]
+async fn sorted() {
+    run! {
+        register();
+
+        let a = module("a.js");
+        let b = module("b.js");
+        let c = module("c.js");
+        let first = InnerAssets::empty()
+            .with_asset("B".to_string(), b)
+            .with_asset("C".to_string(), c)
+            .with_asset("A".to_string(), a);
+        let second = InnerAssets::empty()
+            .with_asset("C".to_string(), c)
+            .with_asset("A".to_string(), a)
+            .with_asset("B".to_string(), b);
+
+        let entries = |assets: Vc<InnerAssets>| async move {
+            let mut entries = Vec::new();
+            for (name, module) in assets.sorted().await?.iter() {
+                entries.push((name.clone(), module.resolve().await?));
+            }
+            anyhow::Ok(entries)
+        };
+        let sorted = entries(first).await?;
+        assert_eq!(
+            sorted.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(),
+            ["A", "B", "C"]
+        );
+        assert_eq!(sorted, entries(second).await?);
+        // The insertion order of the original map is kept.
+        assert_eq!(first.await?.keys().collect::<Vec<_>>(), ["B", "C", "A"]);
+    }
+}
+
+#[tokio::test]
+async fn merge() {
+    run! {
+        register();
+
+        let a1 = module("a1.js");
+        let a2 = module("a2.js");
+        let b = module("b.js");
+        let c = module("c.js");
+        let first = InnerAssets::empty()
+            .with_asset("A".to_string(), a1)
+            .with_asset("B".to_string(), b);
+        let second = InnerAssets::empty()
+            .with_asset("C".to_string(), c)
+            .with_asset("A".to_string(), a2);
+        let merged = InnerAssets::merge(first, second).await?;
+
+        assert_eq!(merged.keys().collect::<Vec<_>>(), ["A", "B", "C"]);
+        assert_eq!(merged["A"].resolve().await?, a2.resolve().await?);
+        assert_eq!(merged["B"].resolve().await?, b.resolve().await?);
+        assert_eq!(merged["C"].resolve().await?, c.resolve().await?);
+    }
+}
+
+#[tokio::test]
+async fn internal_includes_exact() {
+    run! {
+        register();
+
+        let a = module("a.js");
+        let b = module("b.js");
+        let ab = ReferenceType::Internal(
+            InnerAssets::empty()
+                .with_asset("A".to_string(), a)
+                .with_asset("B".to_string(), b),
+        );
+        let ba = ReferenceType::Internal(
+            InnerAssets::empty()
+                .with_asset("B".to_string(), b)
+                .with_asset("A".to_string(), a),
+        );
+        let a_only = ReferenceType::Internal(InnerAssets::empty().with_asset("A".to_string(), a));
+        let swapped = ReferenceType::Internal(
+            InnerAssets::empty()
+                .with_asset("A".to_string(), b)
+                .with_asset("B".to_string(), a),
+        );
+
+        assert!(ab.includes_exact(&ba).await?);
+        assert!(!ab.includes_exact(&a_only).await?);
+        assert!(!a_only.includes_exact(&ab).await?);
+        assert!(!ab.includes_exact(&swapped).await?);
+        // `includes` doesn't look at the inner assets.
+        assert!(ab.includes(&swapped));
+        assert!(!ab.includes_exact(&ReferenceType::Runtime).await?);
+    }
+}
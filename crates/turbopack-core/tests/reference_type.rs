@@ -0,0 +1,500 @@
+#![cfg(test)]
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde_json::json;
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    reference_type::{
+        CommonJsReferenceSubType, ComposeTarget, CssReferenceSubType,
+        EcmaScriptModulesReferenceSubType, EntryReferenceSubType, ImportContext, ImportLayer,
+        ImportMetaProperty, InnerAssets, JsonReferenceSubType, ReferenceType, ResourceHint,
+        TypeScriptReferenceSubType, UrlReferenceSubType, WebAssemblyReferenceSubType,
+    },
+    register,
+    resolve::ModulePart,
+};
+
+register!();
+
+/// Returns a value of every variant and sub type of [ReferenceType].
+async fn all_reference_types() -> Result<Vec<ReferenceType>> {
+    let import_context = ImportContext::new(
+        vec![ImportLayer::Named("base".to_string())],
+        vec!["print".to_string()],
+        vec!["display: grid".to_string()],
+    )
+    .resolve()
+    .await?;
+
+    Ok(vec![
+        ReferenceType::CommonJs(CommonJsReferenceSubType::Require),
+        ReferenceType::CommonJs(CommonJsReferenceSubType::RequireResolve),
+        ReferenceType::CommonJs(CommonJsReferenceSubType::Custom(1)),
+        ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(
+            ModulePart::export("x".to_string()).resolve().await?,
+        )),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ExportAll),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ReExport),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportMeta(
+            ImportMetaProperty::Url,
+        )),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportMeta(
+            ImportMetaProperty::Resolve,
+        )),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Custom(1)),
+        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined),
+        ReferenceType::Css(CssReferenceSubType::AtImport(None)),
+        ReferenceType::Css(CssReferenceSubType::AtImport(Some(import_context))),
+        ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(vec![
+            ImportLayer::Anonymous,
+            ImportLayer::Named("base".to_string()),
+        ])),
+        ReferenceType::Css(CssReferenceSubType::AtImportUrl(None)),
+        ReferenceType::Css(CssReferenceSubType::AtImportUrl(Some(import_context))),
+        ReferenceType::Css(CssReferenceSubType::Use(None)),
+        ReferenceType::Css(CssReferenceSubType::Use(Some("math".to_string()))),
+        ReferenceType::Css(CssReferenceSubType::Forward(None)),
+        ReferenceType::Css(CssReferenceSubType::Forward(Some("list-".to_string()))),
+        ReferenceType::Css(CssReferenceSubType::Compose(None)),
+        ReferenceType::Css(
+            CssReferenceSubType::compose(ComposeTarget::new(vec!["a".to_string()])).await?,
+        ),
+        ReferenceType::Css(CssReferenceSubType::Internal),
+        ReferenceType::Css(CssReferenceSubType::Custom(1)),
+        ReferenceType::Css(CssReferenceSubType::Undefined),
+        ReferenceType::Url(UrlReferenceSubType::EcmaScriptNewUrl),
+        ReferenceType::Url(UrlReferenceSubType::CssUrl),
+        ReferenceType::Url(UrlReferenceSubType::Worker),
+        ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Preload)),
+        ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Prefetch)),
+        ReferenceType::Url(UrlReferenceSubType::Custom(1)),
+        ReferenceType::Url(UrlReferenceSubType::Undefined),
+        ReferenceType::TypeScript(TypeScriptReferenceSubType::Custom(1)),
+        ReferenceType::TypeScript(TypeScriptReferenceSubType::Undefined),
+        ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(
+            ModulePart::export("x".to_string()).resolve().await?,
+        )),
+        ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes),
+        ReferenceType::Json(JsonReferenceSubType::Require),
+        ReferenceType::Json(JsonReferenceSubType::Custom(1)),
+        ReferenceType::Json(JsonReferenceSubType::Undefined),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Sync),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Url),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Module),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Custom(1)),
+        ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Undefined),
+        ReferenceType::Entry(EntryReferenceSubType::Web),
+        ReferenceType::Entry(EntryReferenceSubType::Page),
+        ReferenceType::Entry(EntryReferenceSubType::PagesApi),
+        ReferenceType::Entry(EntryReferenceSubType::AppPage),
+        ReferenceType::Entry(EntryReferenceSubType::AppRoute),
+        ReferenceType::Entry(EntryReferenceSubType::AppClientComponent),
+        ReferenceType::Entry(EntryReferenceSubType::ServerAction),
+        ReferenceType::Entry(EntryReferenceSubType::ServerComponent),
+        ReferenceType::Entry(EntryReferenceSubType::Middleware),
+        ReferenceType::Entry(EntryReferenceSubType::Instrumentation),
+        ReferenceType::Entry(EntryReferenceSubType::Runtime),
+        ReferenceType::Entry(EntryReferenceSubType::Named("edge function".to_string())),
+        ReferenceType::Entry(EntryReferenceSubType::Custom(1)),
+        ReferenceType::Entry(EntryReferenceSubType::Undefined),
+        ReferenceType::Runtime,
+        ReferenceType::Manifest,
+        ReferenceType::Internal(InnerAssets::empty().resolve().await?),
+        ReferenceType::Custom(1),
+        ReferenceType::Undefined,
+    ])
+}
+
+#[tokio::test]
+async fn serialization_round_trip() {
+    run! {
+        register();
+
+        for reference_type in all_reference_types().await? {
+            let serialized = serde_json::to_string(&reference_type)?;
+            let deserialized: ReferenceType = serde_json::from_str(&serialized)?;
+            assert_eq!(deserialized, reference_type, "{serialized}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn as_module_part() {
+    run! {
+        register();
+
+        let part = ModulePart::export("x".to_string()).resolve().await?;
+        let reference_type =
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(part));
+        assert_eq!(reference_type.as_module_part(), Some(part));
+        let reference_type =
+            ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(part));
+        assert_eq!(reference_type.as_module_part(), Some(part));
+
+        for reference_type in all_reference_types().await? {
+            if !matches!(
+                reference_type,
+                ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(_))
+                    | ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(_))
+            ) {
+                assert_eq!(reference_type.as_module_part(), None, "{reference_type}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn import_part_all() {
+    run! {
+        register();
+
+        let all = ReferenceType::EcmaScriptModules(
+            EcmaScriptModulesReferenceSubType::import_part_all().await?,
+        );
+        assert_eq!(
+            all,
+            ReferenceType::EcmaScriptModules(
+                EcmaScriptModulesReferenceSubType::import_part_all().await?
+            )
+        );
+        assert_eq!(all.as_module_part(), Some(ModulePart::exports().resolve().await?));
+
+        let export = ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(
+            ModulePart::export("x".to_string()).resolve().await?,
+        ));
+        let import = ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import);
+        let undefined =
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Undefined);
+        assert!(all.includes(&all));
+        assert!(!all.includes(&export));
+        assert!(!export.includes(&all));
+        assert!(!all.includes(&import));
+        assert!(undefined.includes(&all));
+    }
+}
+
+#[tokio::test]
+async fn inner_assets() {
+    run! {
+        register();
+
+        let inner_assets = InnerAssets::empty().resolve().await?;
+        let reference_type = ReferenceType::Internal(inner_assets);
+        assert_eq!(reference_type.inner_assets(), Some(inner_assets));
+
+        for reference_type in all_reference_types().await? {
+            if !matches!(reference_type, ReferenceType::Internal(_)) {
+                assert_eq!(reference_type.inner_assets(), None, "{reference_type}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn stable_hash() {
+    run! {
+        register();
+
+        let import_context = || {
+            ImportContext {
+                layers: vec![ImportLayer::Named("base".to_string())],
+                supports: vec![],
+                media: vec!["print".to_string()],
+            }
+            .cell()
+        };
+        let a = ReferenceType::Css(CssReferenceSubType::AtImport(Some(import_context())));
+        let b = ReferenceType::Css(CssReferenceSubType::AtImport(Some(import_context())));
+        assert_ne!(a, b);
+        assert_eq!(a.stable_hash().await?, b.stable_hash().await?);
+
+        let compose = |class_names: &[&str]| {
+            ReferenceType::Css(CssReferenceSubType::Compose(Some(
+                ComposeTarget {
+                    class_names: class_names.iter().map(|name| name.to_string()).collect(),
+                }
+                .cell(),
+            )))
+        };
+        assert_ne!(compose(&["a"]), compose(&["a"]));
+        assert_eq!(compose(&["a"]).stable_hash().await?, compose(&["a"]).stable_hash().await?);
+        assert_ne!(compose(&["a"]).stable_hash().await?, compose(&["b"]).stable_hash().await?);
+
+        // Different reference types hash differently.
+        let reference_types = all_reference_types().await?;
+        let mut hashes = HashSet::new();
+        for reference_type in &reference_types {
+            hashes.insert(reference_type.stable_hash().await?);
+        }
+        assert_eq!(hashes.len(), reference_types.len());
+    }
+}
+
+#[tokio::test]
+async fn compose_includes() {
+    run! {
+        register();
+
+        let compose = |class_names: &[&str]| {
+            let class_names = class_names.iter().map(|name| name.to_string()).collect();
+            let target = ComposeTarget::new(class_names);
+            async move {
+                anyhow::Ok(ReferenceType::Css(
+                    CssReferenceSubType::compose(target).await?,
+                ))
+            }
+        };
+        let a = compose(&["a"]).await?;
+        let b = compose(&["b"]).await?;
+        // `composes: a b from "./other.css"`
+        let a_b = compose(&["a", "b"]).await?;
+        let b_a = compose(&["b", "a"]).await?;
+        let any = ReferenceType::Css(CssReferenceSubType::Compose(None));
+
+        assert_eq!(a, compose(&["a"]).await?);
+        assert!(a.includes(&a));
+        assert!(!a.includes(&b));
+        assert!(!a.includes(&a_b));
+        assert!(!a_b.includes(&a));
+        assert!(!a_b.includes(&b_a));
+        assert!(any.includes(&a_b));
+        assert!(a_b.includes(&any));
+        assert!(!a.includes(&ReferenceType::Css(CssReferenceSubType::AtImport(None))));
+    }
+}
+
+#[tokio::test]
+async fn to_json_value() {
+    run! {
+        register();
+
+        let cases = [
+            (
+                ReferenceType::CommonJs(CommonJsReferenceSubType::Require),
+                json!({ "category": "commonjs", "subType": "require", "payload": null }),
+            ),
+            (
+                ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(
+                    ModulePart::export("x".to_string()).resolve().await?,
+                )),
+                json!({
+                    "category": "EcmaScript Modules",
+                    "subType": "part",
+                    "payload": { "part": "export x" },
+                }),
+            ),
+            (
+                ReferenceType::Css(CssReferenceSubType::AtImport(Some(
+                    ImportContext::new(
+                        vec![ImportLayer::Named("base".to_string()), ImportLayer::Anonymous],
+                        vec!["print".to_string()],
+                        vec!["display: grid".to_string()],
+                    )
+                    .resolve()
+                    .await?,
+                ))),
+                json!({
+                    "category": "css",
+                    "subType": "at-import",
+                    "payload": {
+                        "layers": ["base", null],
+                        "media": ["print"],
+                        "supports": ["display: grid"],
+                    },
+                }),
+            ),
+            (
+                ReferenceType::Css(CssReferenceSubType::AtImportLayerOnly(vec![
+                    ImportLayer::Named("base".to_string()),
+                ])),
+                json!({
+                    "category": "css",
+                    "subType": "at-import layer",
+                    "payload": { "layers": ["base"] },
+                }),
+            ),
+            (
+                ReferenceType::Css(
+                    CssReferenceSubType::compose(ComposeTarget::new(vec!["a".to_string()])).await?,
+                ),
+                json!({
+                    "category": "css",
+                    "subType": "compose",
+                    "payload": { "classNames": ["a"] },
+                }),
+            ),
+            (
+                ReferenceType::Url(UrlReferenceSubType::ResourceHint(ResourceHint::Preload)),
+                json!({ "category": "url", "subType": "preload", "payload": null }),
+            ),
+            (
+                ReferenceType::TypeScript(TypeScriptReferenceSubType::Custom(2)),
+                json!({ "category": "typescript", "subType": "custom", "payload": { "id": 2 } }),
+            ),
+            (
+                ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes),
+                json!({
+                    "category": "json",
+                    "subType": "import with attributes",
+                    "payload": null,
+                }),
+            ),
+            (
+                ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(
+                    ModulePart::export("x".to_string()).resolve().await?,
+                )),
+                json!({
+                    "category": "json",
+                    "subType": "part with attributes",
+                    "payload": { "part": "export x" },
+                }),
+            ),
+            (
+                ReferenceType::WebAssembly(WebAssemblyReferenceSubType::Async),
+                json!({ "category": "wasm", "subType": "async", "payload": null }),
+            ),
+            (
+                ReferenceType::Entry(EntryReferenceSubType::Named("page".to_string())),
+                json!({ "category": "entry", "subType": "named", "payload": { "name": "page" } }),
+            ),
+            (
+                ReferenceType::Entry(EntryReferenceSubType::Page),
+                json!({ "category": "entry", "subType": "page", "payload": null }),
+            ),
+            (
+                ReferenceType::Runtime,
+                json!({ "category": "runtime", "subType": null, "payload": null }),
+            ),
+            (
+                ReferenceType::Manifest,
+                json!({ "category": "manifest", "subType": null, "payload": null }),
+            ),
+            (
+                ReferenceType::Internal(InnerAssets::empty().resolve().await?),
+                json!({
+                    "category": "internal",
+                    "subType": null,
+                    "payload": { "innerAssets": [] },
+                }),
+            ),
+            (
+                ReferenceType::custom_with_name(210, "json test")?,
+                json!({
+                    "category": "custom",
+                    "subType": null,
+                    "payload": { "id": 210, "name": "json test" },
+                }),
+            ),
+            (
+                ReferenceType::Undefined,
+                json!({ "category": "undefined", "subType": null, "payload": null }),
+            ),
+        ];
+        for (reference_type, expected) in cases {
+            assert_eq!(reference_type.to_json_value().await?, expected, "{reference_type}");
+        }
+
+        // Every reference type has the same shape, and the category is the
+        // one of its display name.
+        for reference_type in all_reference_types().await? {
+            let value = reference_type.to_json_value().await?;
+            let object = value.as_object().unwrap();
+            assert_eq!(
+                object.keys().collect::<Vec<_>>(),
+                ["category", "subType", "payload"],
+                "{reference_type}"
+            );
+            let category = object["category"].as_str().unwrap();
+            assert!(reference_type.to_string().starts_with(category), "{reference_type}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn at_import_url() {
+    run! {
+        register();
+
+        let print = ImportContext::new(vec![], vec!["print".to_string()], vec![]);
+        let quoted = ReferenceType::Css(CssReferenceSubType::at_import(print).await?);
+        let url = ReferenceType::Css(CssReferenceSubType::at_import_url(print).await?);
+
+        // The syntax is preserved, but doesn't affect which references match.
+        assert_ne!(quoted, url);
+        assert!(matches!(
+            url,
+            ReferenceType::Css(CssReferenceSubType::AtImportUrl(Some(_)))
+        ));
+        assert_eq!(url.to_string(), "css (at-import url)");
+        assert!(quoted.includes(&url));
+        assert!(url.includes(&quoted));
+
+        let screen = ImportContext::new(vec![], vec!["screen".to_string()], vec![]);
+        let screen = ReferenceType::Css(CssReferenceSubType::at_import_url(screen).await?);
+        assert!(!url.includes(&screen));
+        assert!(!quoted.includes(&screen));
+
+        // Without conditions, `url()` imports match any `@import`.
+        let empty = ImportContext::new(vec![], vec![], vec![]);
+        let any_url = ReferenceType::Css(CssReferenceSubType::at_import_url(empty).await?);
+        assert_eq!(any_url, ReferenceType::Css(CssReferenceSubType::AtImportUrl(None)));
+        assert!(any_url.includes(&quoted));
+        assert!(ReferenceType::Css(CssReferenceSubType::AtImport(None)).includes(&url));
+
+        // Layer only contexts are only matched exactly.
+        let layer = ImportContext::new(vec![ImportLayer::Named("a".to_string())], vec![], vec![]);
+        let layer_quoted = ReferenceType::Css(CssReferenceSubType::at_import(layer).await?);
+        let layer_url = ReferenceType::Css(CssReferenceSubType::at_import_url(layer).await?);
+        assert!(!layer_url.includes(&layer_quoted));
+        assert!(layer_url.includes_exact(&layer_quoted).await?);
+        assert!(layer_quoted.includes_exact(&layer_url).await?);
+        assert!(!layer_url.includes_exact(&quoted).await?);
+    }
+}
+
+#[tokio::test]
+async fn sass_use_and_forward() {
+    run! {
+        register();
+
+        // `@use "sass:math"`
+        let default = ReferenceType::Css(CssReferenceSubType::Use(None));
+        // `@use "sass:math" as m`
+        let aliased = ReferenceType::Css(CssReferenceSubType::Use(Some("m".to_string())));
+        // `@use "sass:math" as *`
+        let global = ReferenceType::Css(CssReferenceSubType::Use(Some("*".to_string())));
+        // `@forward "sass:math"`
+        let forward = ReferenceType::Css(CssReferenceSubType::Forward(None));
+
+        assert_eq!(default.to_string(), "css (use)");
+        assert_eq!(forward.to_string(), "css (forward)");
+        assert!(default.includes(&default));
+        assert!(aliased.includes(&aliased));
+        for (a, b) in [(&default, &aliased), (&aliased, &global), (&default, &forward)] {
+            assert!(!a.includes(b), "{a:?} {b:?}");
+            assert!(!b.includes(a), "{a:?} {b:?}");
+            assert_ne!(a.stable_hash().await?, b.stable_hash().await?);
+        }
+
+        // Sass modules aren't loaded like `@import`s.
+        let at_import = ReferenceType::Css(CssReferenceSubType::AtImport(None));
+        assert!(!at_import.includes(&default));
+        assert!(!default.includes(&at_import));
+        assert!(ReferenceType::Css(CssReferenceSubType::Undefined).includes(&aliased));
+
+        assert_eq!(
+            aliased.to_json_value().await?,
+            json!({ "category": "css", "subType": "use", "payload": { "namespace": "m" } })
+        );
+        assert_eq!(
+            forward.to_json_value().await?,
+            json!({ "category": "css", "subType": "forward", "payload": { "prefix": null } })
+        );
+    }
+}
@@ -0,0 +1,1886 @@
+#![cfg(test)]
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use turbo_tasks::{Value, Vc};
+use turbo_tasks_fs::{DiskFileSystem, File, FileContent, FileSystem, FileSystemPath};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    file_source::FileSource,
+    issue::{Issue, IssueDescriptionExt, IssueSeverity, IssueSource, StyledString},
+    reference_type::{
+        CommonJsReferenceSubType, EcmaScriptModulesReferenceSubType, JsonReferenceSubType,
+        ReferenceType,
+    },
+    register,
+    resolve::{
+        handle_resolve_error,
+        node::{
+            node_cjs_resolve_options, node_cjs_resolve_options_for_browser,
+            node_cjs_resolve_options_with_context, node_esm_resolve_options,
+            node_esm_resolve_options_with_context, node_style_resolve_options,
+            node_ts_resolve_options, NodeResolveOptionsContext, ResolveMode,
+        },
+        options::{
+            merge_resolution_conditions, BundledSources, ConditionValue, ResolutionConditions,
+            ResolveInPackage, ResolveIntoPackage, ResolveModules, ResolveOptions,
+            ResolveOptionsPatch,
+        },
+        parse::Request,
+        resolve, ModuleResolveResult, ResolveResultItem,
+    },
+    source::Source,
+};
+
+register!();
+
+/// Returns the `tests/resolve` directory which contains the fixtures.
+fn fixtures_root() -> Vc<FileSystemPath> {
+    let fs: Vc<Box<dyn FileSystem>> = Vc::upcast(DiskFileSystem::new(
+        "fixtures".to_string(),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/resolve").to_string(),
+        vec![],
+    ));
+    fs.root()
+}
+
+/// Resolves `request` inside of the `fixture` directory in `tests/resolve`
+/// with the options returned by `options` for the fixtures root. Returns the
+/// paths of all resolved sources relative to the fixture directory, followed
+/// by their query and fragment, if any.
+async fn resolve_fixture(
+    fixture: &str,
+    request: &str,
+    options: impl FnOnce(Vc<FileSystemPath>) -> Vc<ResolveOptions>,
+) -> Result<Vec<String>> {
+    let fixture_path = fixtures_root().join(fixture.to_string());
+    let result = resolve(
+        fixture_path,
+        Value::new(ReferenceType::Undefined),
+        Request::parse_string(request.to_string()),
+        options(fixtures_root()),
+    )
+    .await?;
+
+    let fixture_path = fixture_path.await?;
+    let mut paths = Vec::new();
+    for item in result.primary.values() {
+        if let ResolveResultItem::Source(source) = item {
+            let ident = source.ident().await?;
+            let path = ident.path.await?;
+            let query = ident.query.await?;
+            let fragment = match ident.fragment {
+                Some(fragment) => format!("#{}", fragment.await?),
+                None => String::new(),
+            };
+            paths.push(format!(
+                "{}{}{}",
+                fixture_path.get_path_to(&path).unwrap(),
+                query,
+                fragment
+            ));
+        }
+    }
+    Ok(paths)
+}
+
+/// Like [resolve_fixture], but returns the details of the issues emitted
+/// while resolving.
+async fn resolve_fixture_issues(
+    fixture: &str,
+    request: &str,
+    options: impl FnOnce(Vc<FileSystemPath>) -> Vc<ResolveOptions>,
+) -> Result<Vec<String>> {
+    resolve_fixture_issues_with_reference_type(fixture, request, ReferenceType::Undefined, options)
+        .await
+}
+
+/// Like [resolve_fixture_issues], but resolves for the given reference type.
+async fn resolve_fixture_issues_with_reference_type(
+    fixture: &str,
+    request: &str,
+    reference_type: ReferenceType,
+    options: impl FnOnce(Vc<FileSystemPath>) -> Vc<ResolveOptions>,
+) -> Result<Vec<String>> {
+    let result = resolve(
+        fixtures_root().join(fixture.to_string()),
+        Value::new(reference_type),
+        Request::parse_string(request.to_string()),
+        options(fixtures_root()),
+    )
+    .resolve_strongly_consistent()
+    .await?;
+
+    let mut details = Vec::new();
+    for (issue, _) in result
+        .peek_issues_with_path()
+        .await?
+        .iter_with_shortest_path()
+    {
+        if let Some(detail) = *issue.detail().await? {
+            if let StyledString::Text(detail) = &*detail.await? {
+                details.push(detail.clone());
+            }
+        }
+    }
+    Ok(details)
+}
+
+/// Resolves `request` from the `index.js` of `fixture`, and handles the result
+/// like a reference does, with an issue source pointing at `index.js`.
+#[turbo_tasks::function]
+async fn handle_fixture_result(
+    fixture: String,
+    request: String,
+    reference_type: Value<ReferenceType>,
+    options: Vc<ResolveOptions>,
+) -> Result<Vc<ModuleResolveResult>> {
+    let fixture_path = fixtures_root().join(fixture);
+    let origin_path = fixture_path.join("index.js".to_string());
+    let request = Request::parse_string(request);
+    let result =
+        resolve(fixture_path, reference_type.clone(), request, options).as_raw_module_result();
+    let issue_source = IssueSource::from_source_only(Vc::upcast(FileSource::new(origin_path)));
+    handle_resolve_error(
+        result,
+        reference_type,
+        origin_path,
+        request,
+        options,
+        IssueSeverity::Error.cell(),
+        Some(issue_source),
+    )
+    .await
+}
+
+/// Returns the detail of each issue [handle_fixture_result] emits, and whether
+/// the issue has a source.
+async fn handle_fixture_issues(
+    fixture: &str,
+    request: &str,
+    reference_type: ReferenceType,
+    options: impl FnOnce(Vc<FileSystemPath>) -> Vc<ResolveOptions>,
+) -> Result<Vec<(String, bool)>> {
+    let result = handle_fixture_result(
+        fixture.to_string(),
+        request.to_string(),
+        Value::new(reference_type),
+        options(fixtures_root()),
+    )
+    .resolve_strongly_consistent()
+    .await?;
+
+    let mut issues = Vec::new();
+    for (issue, _) in result
+        .peek_issues_with_path()
+        .await?
+        .iter_with_shortest_path()
+    {
+        if let Some(detail) = *issue.detail().await? {
+            if let StyledString::Text(detail) = &*detail.await? {
+                issues.push((detail.clone(), issue.source().await?.is_some()));
+            }
+        }
+    }
+    Ok(issues)
+}
+
+#[tokio::test]
+async fn browser_field_string() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("browser-field", "browser-string", node_cjs_resolve_options_for_browser)
+                .await?,
+            vec!["node_modules/browser-string/browser.js"]
+        );
+        assert_eq!(
+            resolve_fixture("browser-field", "browser-string", node_cjs_resolve_options).await?,
+            vec!["node_modules/browser-string/node.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn browser_field_object() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("browser-field", "browser-object", node_cjs_resolve_options_for_browser)
+                .await?,
+            vec!["node_modules/browser-object/lib/browser.js"]
+        );
+        assert_eq!(
+            resolve_fixture("browser-field", "browser-object", node_cjs_resolve_options).await?,
+            vec!["node_modules/browser-object/lib/node.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn browser_field_false() {
+    run! {
+        register();
+
+        let package = "browser-field/node_modules/browser-false";
+        for request in ["fs", "./lib/node-only.js"] {
+            let result = resolve(
+                fixtures_root().join(package.to_string()),
+                Value::new(ReferenceType::Undefined),
+                Request::parse_string(request.to_string()),
+                node_cjs_resolve_options_for_browser(fixtures_root()),
+            )
+            .await?;
+            assert!(
+                matches!(
+                    result.primary.values().collect::<Vec<_>>()[..],
+                    [ResolveResultItem::Empty]
+                ),
+                "{request} should resolve to an empty module"
+            );
+        }
+        assert_eq!(
+            resolve_fixture(package, "./lib/node-only.js", node_cjs_resolve_options).await?,
+            vec!["lib/node-only.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn module_field() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("module-field", "module-field", node_esm_resolve_options).await?,
+            vec!["node_modules/module-field/module.mjs"]
+        );
+        assert_eq!(
+            resolve_fixture("module-field", "module-field", node_cjs_resolve_options).await?,
+            vec!["node_modules/module-field/main.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn main_fields() {
+    run! {
+        register();
+
+        let with_main_fields = |fields: &[&str]| {
+            let fields = fields.iter().map(|field| field.to_string()).collect();
+            move |root| {
+                ResolveOptions {
+                    extensions: vec![".js".to_string()],
+                    modules: vec![ResolveModules::Nested(root, vec!["node_modules".to_string()])],
+                    into_package: vec![ResolveIntoPackage::MainFields { fields }],
+                    default_files: vec!["index".to_string()],
+                    ..Default::default()
+                }
+                .cell()
+            }
+        };
+
+        // The first field which is present wins.
+        for (request, expected) in [
+            ("all-fields", "node_modules/all-fields/browser.js"),
+            ("module-main", "node_modules/module-main/module.js"),
+            ("main-only", "node_modules/main-only/main.js"),
+        ] {
+            assert_eq!(
+                resolve_fixture(
+                    "main-fields",
+                    request,
+                    with_main_fields(&["browser", "module", "main"])
+                )
+                .await?,
+                vec![expected]
+            );
+        }
+        assert_eq!(
+            resolve_fixture("main-fields", "all-fields", with_main_fields(&["main", "module"]))
+                .await?,
+            vec!["node_modules/all-fields/main.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn bundled() {
+    run! {
+        register();
+
+        let with_bundled = |bundled: bool| {
+            move |root: Vc<FileSystemPath>| {
+                let mut modules =
+                    vec![ResolveModules::Nested(root, vec!["node_modules".to_string()])];
+                if bundled {
+                    let polyfill = FileSource::new(root.join("bundled/polyfills/buffer.js"));
+                    let sources = IndexMap::from([("buffer".to_string(), Vc::upcast(polyfill))]);
+                    modules.push(ResolveModules::Bundled(BundledSources::cell(sources)));
+                }
+                ResolveOptions {
+                    extensions: vec![".js".to_string()],
+                    modules,
+                    default_files: vec!["index".to_string()],
+                    ..Default::default()
+                }
+                .cell()
+            }
+        };
+
+        // The bundled module wins over the package in node_modules.
+        assert_eq!(
+            resolve_fixture("bundled", "buffer", with_bundled(true)).await?,
+            vec!["polyfills/buffer.js"]
+        );
+        assert_eq!(
+            resolve_fixture("bundled", "buffer", with_bundled(false)).await?,
+            vec!["node_modules/buffer/index.js"]
+        );
+        // Subpaths are still resolved inside of the package.
+        assert_eq!(
+            resolve_fixture("bundled", "buffer/index", with_bundled(true)).await?,
+            vec!["node_modules/buffer/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn directory_main_field() {
+    run! {
+        register();
+
+        // `lib/package.json` points to `impl`, which has a package.json of its
+        // own. Both take precedence over the `index.js` files.
+        assert_eq!(
+            resolve_fixture("directory-main", "./lib", node_cjs_resolve_options).await?,
+            vec!["lib/impl/entry.js"]
+        );
+        assert_eq!(
+            resolve_fixture("directory-main", "./plain", node_cjs_resolve_options).await?,
+            vec!["plain/index.js"]
+        );
+
+        assert_eq!(
+            resolve_fixture("directory-main", "./esm", node_cjs_resolve_options).await?,
+            vec!["esm/main.js"]
+        );
+        let context = NodeResolveOptionsContext {
+            fully_specified: Some(false),
+            ..Default::default()
+        }
+        .cell();
+        assert_eq!(
+            resolve_fixture("directory-main", "./esm", |root| {
+                node_esm_resolve_options_with_context(root, context)
+            })
+            .await?,
+            vec!["esm/module.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn additional_extensions() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            extensions: vec![".ts".to_string(), ".js".to_string(), ".ts".to_string()],
+            ..Default::default()
+        }
+        .cell();
+        let with_ts = |root| node_cjs_resolve_options_with_context(root, context);
+
+        assert_eq!(
+            resolve_fixture("extensions", "./file", with_ts).await?,
+            vec!["file.ts"]
+        );
+        assert!(resolve_fixture("extensions", "./file", node_cjs_resolve_options).await?.is_empty());
+
+        assert_eq!(
+            with_ts(fixtures_root()).await?.extensions,
+            vec![".js", ".cjs", ".json", ".node", ".ts"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn module_extensions() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("module-extensions", "esm-only", node_esm_resolve_options).await?,
+            vec!["node_modules/esm-only/lib/entry.mjs"]
+        );
+        assert!(resolve_fixture("module-extensions", "esm-only", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+
+        assert_eq!(
+            resolve_fixture("module-extensions", "./foo", node_cjs_resolve_options).await?,
+            vec!["foo.cjs"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn package_extensions() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            extensions: vec![".ts".to_string()],
+            ..Default::default()
+        }
+        .cell();
+        let with_ts = |root| node_cjs_resolve_options_with_context(root, context);
+        let js_packages = |root| {
+            ResolveOptions::merge(
+                with_ts(root),
+                ResolveOptionsPatch {
+                    package_extensions: Some(vec![".js".to_string(), ".mjs".to_string()]),
+                    ..Default::default()
+                }
+                .cell(),
+            )
+        };
+
+        assert_eq!(
+            resolve_fixture("package-extensions", "ts-only", with_ts).await?,
+            vec!["node_modules/ts-only/index.ts"]
+        );
+        assert!(resolve_fixture("package-extensions", "ts-only", js_packages).await?.is_empty());
+        assert!(resolve_fixture("package-extensions", "ts-only/util", js_packages)
+            .await?
+            .is_empty());
+        assert!(resolve_fixture("package-extensions", "esm", with_ts).await?.is_empty());
+        assert_eq!(
+            resolve_fixture("package-extensions", "esm", js_packages).await?,
+            vec!["node_modules/esm/index.mjs"]
+        );
+
+        // Explicit extensions and exports field targets are not affected.
+        assert_eq!(
+            resolve_fixture("package-extensions", "ts-only/util.ts", js_packages).await?,
+            vec!["node_modules/ts-only/util.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("package-extensions", "exports-ts", js_packages).await?,
+            vec!["node_modules/exports-ts/index.ts"]
+        );
+
+        // Relative requests still use the extensions.
+        assert_eq!(
+            resolve_fixture("package-extensions", "./local", js_packages).await?,
+            vec!["local.ts"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn fully_specified() {
+    run! {
+        register();
+
+        let context = |fully_specified| {
+            NodeResolveOptionsContext {
+                fully_specified: Some(fully_specified),
+                ..Default::default()
+            }
+            .cell()
+        };
+
+        assert_eq!(
+            resolve_fixture("fully-specified", "./dir", |root| {
+                node_esm_resolve_options_with_context(root, context(false))
+            })
+            .await?,
+            vec!["dir/index.js"]
+        );
+        assert!(resolve_fixture("fully-specified", "./dir", |root| {
+            node_cjs_resolve_options_with_context(root, context(true))
+        })
+        .await?
+        .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn default_condition() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("default-condition", "default-only", node_cjs_resolve_options).await?,
+            vec!["node_modules/default-only/x.js"]
+        );
+        assert_eq!(
+            resolve_fixture("default-condition", "default-only", node_esm_resolve_options).await?,
+            vec!["node_modules/default-only/x.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn exports_conditions_case_sensitive() {
+    run! {
+        register();
+
+        // Conditions are matched exactly, so `Import` is an unknown condition
+        // which doesn't match `import`, and `Default` isn't `default`.
+        for options in [node_cjs_resolve_options, node_esm_resolve_options] {
+            assert_eq!(
+                resolve_fixture("condition-case", "wrong-case", options).await?,
+                vec!["node_modules/wrong-case/index.js"]
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn prefer_relative() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            prefer_relative: true,
+            ..Default::default()
+        }
+        .cell();
+        let prefer_relative = |root| node_cjs_resolve_options_with_context(root, context);
+
+        // By default, a module request only resolves to the package.
+        assert_eq!(
+            resolve_fixture("prefer-relative", "foo", node_cjs_resolve_options).await?,
+            vec!["node_modules/foo/index.js"]
+        );
+        // With `prefer_relative`, the local file takes precedence.
+        assert_eq!(
+            resolve_fixture("prefer-relative", "foo", prefer_relative).await?,
+            vec!["foo.js"]
+        );
+        // Without a local file, the package is still found.
+        assert_eq!(
+            resolve_fixture("prefer-relative", "only-package", prefer_relative).await?,
+            vec!["node_modules/only-package/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn module_dirs() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            module_dirs: Some(vec!["node_modules".to_string(), "vendor".to_string()]),
+            ..Default::default()
+        }
+        .cell();
+
+        assert_eq!(
+            resolve_fixture("module-dirs", "vendored", |root| {
+                node_cjs_resolve_options_with_context(root, context)
+            })
+            .await?,
+            vec!["vendor/vendored/index.js"]
+        );
+        assert!(resolve_fixture("module-dirs", "vendored", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn boundary() {
+    run! {
+        register();
+
+        let with_boundary = |root: Vc<FileSystemPath>| {
+            ResolveOptions::merge(
+                node_cjs_resolve_options(root),
+                ResolveOptionsPatch {
+                    boundary: Some(root.join("boundary/project".to_string())),
+                    ..Default::default()
+                }
+                .cell(),
+            )
+        };
+
+        // Packages inside of the boundary are still found.
+        assert_eq!(
+            resolve_fixture("boundary/project", "inside", with_boundary).await?,
+            vec!["node_modules/inside/index.js"]
+        );
+        assert!(resolve_fixture("boundary/project", "outside", with_boundary)
+            .await?
+            .is_empty());
+
+        // Without a boundary, the package above the project is found.
+        let result = resolve(
+            fixtures_root().join("boundary/project".to_string()),
+            Value::new(ReferenceType::Undefined),
+            Request::parse_string("outside".to_string()),
+            node_cjs_resolve_options(fixtures_root()),
+        )
+        .await?;
+        assert!(!result.is_unresolveable_ref());
+    }
+}
+
+#[tokio::test]
+async fn module_roots() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            module_roots: vec![fixtures_root().join("module-roots/shared".to_string())],
+            ..Default::default()
+        }
+        .cell();
+
+        assert_eq!(
+            resolve_fixture("module-roots", "shared-only", |root| {
+                node_cjs_resolve_options_with_context(root, context)
+            })
+            .await?,
+            vec!["shared/shared-only/index.js"]
+        );
+        // Packages in `node_modules` take precedence over the module roots.
+        assert_eq!(
+            resolve_fixture("module-roots", "both", |root| {
+                node_cjs_resolve_options_with_context(root, context)
+            })
+            .await?,
+            vec!["node_modules/both/index.js"]
+        );
+        assert!(resolve_fixture("module-roots", "shared-only", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn exports_field_patterns() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("exports-patterns", "patterns/features/button", node_cjs_resolve_options)
+                .await?,
+            vec!["node_modules/patterns/src/features/button.js"]
+        );
+        // The capture can span multiple path segments.
+        assert_eq!(
+            resolve_fixture("exports-patterns", "patterns/nested/a/b/index", node_cjs_resolve_options)
+                .await?,
+            vec!["node_modules/patterns/src/nested/a/b/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn exports_field_array_fallbacks() {
+    run! {
+        register();
+
+        let resolve = |request: &'static str| {
+            resolve_fixture("exports-fallbacks", request, node_cjs_resolve_options)
+        };
+        // Later entries are used when the target of the first one doesn't exist.
+        assert_eq!(
+            resolve("fallbacks/array").await?,
+            vec!["node_modules/fallbacks/b.js"]
+        );
+        assert_eq!(
+            resolve("fallbacks/first").await?,
+            vec!["node_modules/fallbacks/a.js"]
+        );
+        // Conditions are respected at each level of nested arrays.
+        assert_eq!(
+            resolve("fallbacks/nested").await?,
+            vec!["node_modules/fallbacks/b.js"]
+        );
+        // A `null` entry stops the fallbacks.
+        assert!(resolve("fallbacks/blocked").await?.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn exports_field_null_target() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("exports-null", "pkg/public", node_cjs_resolve_options).await?,
+            vec!["node_modules/pkg/src/public.js"]
+        );
+        // The file exists and matches `./*`, but the more specific `null` target
+        // blocks it.
+        assert!(resolve_fixture("exports-null", "pkg/internal/secret", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+
+        let details =
+            resolve_fixture_issues("exports-null", "pkg/internal/secret", node_cjs_resolve_options)
+                .await?;
+        assert_eq!(details.len(), 1, "{details:?}");
+        assert!(
+            details[0].contains(
+                "the subpath `./internal/secret` is blocked by a `null` target in the exports \
+                 field"
+            ),
+            "{}",
+            details[0]
+        );
+        assert!(
+            resolve_fixture_issues("exports-null", "pkg/public", node_cjs_resolve_options)
+                .await?
+                .is_empty()
+        );
+    }
+}
+
+#[tokio::test]
+async fn exports_root_conditions() {
+    run! {
+        register();
+
+        // Only the root is exported, with conditions.
+        assert_eq!(
+            resolve_fixture("exports-root", "root-only", node_cjs_resolve_options).await?,
+            vec!["node_modules/root-only/r.cjs"]
+        );
+        assert_eq!(
+            resolve_fixture("exports-root", "root-only", node_esm_resolve_options).await?,
+            vec!["node_modules/root-only/i.mjs"]
+        );
+
+        // The root and a subpath, both with conditions.
+        for (request, cjs, esm) in [
+            ("mixed", "node_modules/mixed/index.js", "node_modules/mixed/i.mjs"),
+            ("mixed/sub", "node_modules/mixed/sub/index.js", "node_modules/mixed/sub/i.mjs"),
+        ] {
+            assert_eq!(
+                resolve_fixture("exports-root", request, node_cjs_resolve_options).await?,
+                vec![cjs]
+            );
+            assert_eq!(
+                resolve_fixture("exports-root", request, node_esm_resolve_options).await?,
+                vec![esm]
+            );
+        }
+        assert!(resolve_fixture("exports-root", "mixed/i.mjs", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn exports_string() {
+    run! {
+        register();
+
+        // The string form only exports the package root, and takes precedence
+        // over the main field.
+        assert_eq!(
+            resolve_fixture("exports-string", "string-exports", node_cjs_resolve_options).await?,
+            vec!["node_modules/string-exports/index.js"]
+        );
+        assert!(
+            resolve_fixture("exports-string", "string-exports/feature.js", node_cjs_resolve_options)
+                .await?
+                .is_empty()
+        );
+
+        let options = |root| {
+            ResolveOptions::merge(
+                node_cjs_resolve_options(root),
+                ResolveOptionsPatch {
+                    exports_field_diagnostics: Some(true),
+                    ..Default::default()
+                }
+                .cell(),
+            )
+        };
+        let details =
+            resolve_fixture_issues("exports-string", "string-exports/feature.js", options).await?;
+        assert_eq!(details.len(), 1, "{details:?}");
+        assert!(details[0].contains("Available subpaths: ."), "{}", details[0]);
+    }
+}
+
+#[tokio::test]
+async fn exports_field_diagnostics() {
+    run! {
+        register();
+
+        let options = |root| {
+            ResolveOptions::merge(
+                node_cjs_resolve_options(root),
+                ResolveOptionsPatch {
+                    exports_field_diagnostics: Some(true),
+                    ..Default::default()
+                }
+                .cell(),
+            )
+        };
+
+        // No subpath matches.
+        let details = resolve_fixture_issues("exports-patterns", "patterns/feature", options).await?;
+        assert_eq!(details.len(), 1, "{details:?}");
+        let detail = &details[0];
+        assert!(
+            detail.contains(
+                "`./feature` could not be resolved with the exports field of package `patterns`"
+            ),
+            "{detail}"
+        );
+        for subpath in [
+            "./features/*",
+            "./features/internal/*",
+            "./features/node/*",
+            "./nested/*/index",
+        ] {
+            assert!(detail.contains(subpath), "{detail}");
+        }
+        assert!(
+            detail.contains("Nearest matching subpaths: ./features/*, ./features/internal/*, \
+                             ./features/node/*"),
+            "{detail}"
+        );
+        assert!(detail.contains("Set conditions: default, node, require"), "{detail}");
+
+        // The subpath matches, but none of its conditions do.
+        let details =
+            resolve_fixture_issues("exports-patterns", "patterns/features/node/fs", options).await?;
+        assert_eq!(details.len(), 1, "{details:?}");
+        assert!(details[0].contains("Unset conditions: browser"), "{}", details[0]);
+
+        // Without the flag, there is no diagnostic.
+        assert!(
+            resolve_fixture_issues("exports-patterns", "patterns/feature", node_cjs_resolve_options)
+                .await?
+                .is_empty()
+        );
+    }
+}
+
+/// Resolves `request` inside of the `fixture` directory with the exports
+/// field trace enabled and returns the matched conditions and the chosen
+/// target of each trace.
+async fn resolve_fixture_traces(
+    fixture: &str,
+    request: &str,
+    options: impl FnOnce(Vc<FileSystemPath>) -> Vc<ResolveOptions>,
+) -> Result<Vec<(Vec<String>, Option<String>)>> {
+    let options = ResolveOptions::merge(
+        options(fixtures_root()),
+        ResolveOptionsPatch {
+            exports_field_trace: Some(true),
+            ..Default::default()
+        }
+        .cell(),
+    );
+    let result = resolve(
+        fixtures_root().join(fixture.to_string()),
+        Value::new(ReferenceType::Undefined),
+        Request::parse_string(request.to_string()),
+        options,
+    )
+    .await?;
+    Ok(result
+        .exports_field_traces
+        .iter()
+        .map(|trace| (trace.conditions.clone(), trace.target.clone()))
+        .collect())
+}
+
+#[tokio::test]
+async fn exports_field_trace() {
+    run! {
+        register();
+
+        let trace = |conditions: &[&str], target: &str| {
+            (
+                conditions.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+                Some(target.to_string()),
+            )
+        };
+
+        assert_eq!(
+            resolve_fixture_traces("exports-trace", "nested-conditions", node_cjs_resolve_options)
+                .await?,
+            vec![trace(&["node", "require", "default"], "./node.js")]
+        );
+        assert_eq!(
+            resolve_fixture_traces("exports-trace", "nested-conditions", node_esm_resolve_options)
+                .await?,
+            vec![trace(&["node", "import"], "./node.mjs")]
+        );
+
+        let context = NodeResolveOptionsContext {
+            mode: Some(ResolveMode::Production),
+            ..Default::default()
+        }
+        .cell();
+        let production = |root| node_cjs_resolve_options_with_context(root, context);
+        assert_eq!(
+            resolve_fixture_traces("exports-trace", "nested-conditions", production).await?,
+            vec![trace(&["node", "require", "production"], "./production.js")]
+        );
+
+        // Without the flag, no trace is recorded.
+        let fixture_path = fixtures_root().join("exports-trace".to_string());
+        let result = resolve(
+            fixture_path,
+            Value::new(ReferenceType::Undefined),
+            Request::parse_string("nested-conditions".to_string()),
+            node_cjs_resolve_options(fixtures_root()),
+        )
+        .await?;
+        assert!(result.exports_field_traces.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn strict_exports() {
+    run! {
+        register();
+
+        // `pkg` in `node_modules` has an exports field which only exports
+        // `.`, and a `main` field pointing elsewhere. Another `pkg` without an
+        // exports field is in the `vendor` module root.
+        let context = NodeResolveOptionsContext {
+            module_roots: vec![fixtures_root().join("strict-exports/vendor".to_string())],
+            ..Default::default()
+        }
+        .cell();
+        let options = |strict_exports| {
+            move |root| {
+                ResolveOptions::merge(
+                    node_cjs_resolve_options_with_context(root, context),
+                    ResolveOptionsPatch {
+                        strict_exports: Some(strict_exports),
+                        ..Default::default()
+                    }
+                    .cell(),
+                )
+            }
+        };
+
+        for strict_exports in [true, false] {
+            assert_eq!(
+                resolve_fixture("strict-exports", "pkg", options(strict_exports)).await?,
+                vec!["node_modules/pkg/exported.js"]
+            );
+        }
+        // Without strict exports, the unexported subpath is resolved in the
+        // other package.
+        assert_eq!(
+            resolve_fixture("strict-exports", "pkg/extra.js", options(false)).await?,
+            vec!["vendor/pkg/extra.js"]
+        );
+        assert!(resolve_fixture("strict-exports", "pkg/extra.js", options(true))
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn symlinks() {
+    run! {
+        register();
+
+        // `node_modules/linked-pkg` is a symlink to `packages/real-pkg`.
+        for request in ["linked-pkg", "./packages/real-pkg", "./node_modules/linked-pkg"] {
+            assert_eq!(
+                resolve_fixture("symlinks", request, node_cjs_resolve_options).await?,
+                vec!["packages/real-pkg/index.js"],
+                "{request}"
+            );
+        }
+
+        let context = NodeResolveOptionsContext {
+            preserve_symlinks: true,
+            ..Default::default()
+        }
+        .cell();
+        let options = |root| node_cjs_resolve_options_with_context(root, context);
+        for request in ["linked-pkg", "./node_modules/linked-pkg"] {
+            assert_eq!(
+                resolve_fixture("symlinks", request, options).await?,
+                vec!["node_modules/linked-pkg/index.js"],
+                "{request}"
+            );
+        }
+        assert_eq!(
+            resolve_fixture("symlinks", "./packages/real-pkg", options).await?,
+            vec!["packages/real-pkg/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn exports_field_longest_match_wins() {
+    run! {
+        register();
+
+        // `./features/internal/*` is excluded, even though `./features/*` would
+        // match too.
+        assert!(resolve_fixture(
+            "exports-patterns",
+            "patterns/features/internal/secret",
+            node_cjs_resolve_options
+        )
+        .await?
+        .is_empty());
+        // The conditions of `./features/node/*` don't match, which doesn't fall
+        // back to `./features/*`.
+        assert!(resolve_fixture(
+            "exports-patterns",
+            "patterns/features/node/x",
+            node_cjs_resolve_options
+        )
+        .await?
+        .is_empty());
+        assert_eq!(
+            resolve_fixture(
+                "exports-patterns",
+                "patterns/features/node/x",
+                node_cjs_resolve_options_for_browser
+            )
+            .await?,
+            vec!["node_modules/patterns/src/features/node/x.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn imports_field() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("imports-field", "#x", node_cjs_resolve_options).await?,
+            vec!["src/x.js"]
+        );
+        assert_eq!(
+            resolve_fixture("imports-field", "#internal/util", node_esm_resolve_options).await?,
+            vec!["src/internal/util.js"]
+        );
+        assert!(resolve_fixture("imports-field", "#missing", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn imports_field_package_target() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("imports-field", "#dep", node_cjs_resolve_options).await?,
+            vec!["node_modules/dep/index.js"]
+        );
+        // Requests without a `#` prefix don't use the imports field.
+        assert_eq!(
+            resolve_fixture("imports-field", "dep", node_cjs_resolve_options).await?,
+            vec!["node_modules/dep/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn query() {
+    run! {
+        register();
+
+        for (request, expected) in [
+            ("./img.js?inline", "img.js?inline"),
+            ("./dir?inline", "dir/index.js?inline"),
+            ("pkg?raw", "node_modules/pkg/lib/index.js?raw"),
+            ("pkg/x?raw", "node_modules/pkg/lib/x.js?raw"),
+            ("pkg/assets/icon?raw", "node_modules/pkg/lib/assets/icon.js?raw"),
+            ("main-pkg?raw", "node_modules/main-pkg/lib/main.js?raw"),
+            ("pkg/x", "node_modules/pkg/lib/x.js"),
+        ] {
+            assert_eq!(
+                resolve_fixture("query", request, node_cjs_resolve_options).await?,
+                vec![expected],
+                "{request}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn fragment() {
+    run! {
+        register();
+
+        // A leading `#` is an imports field specifier, a later one starts a
+        // fragment which is preserved on the source. If the request doesn't
+        // resolve without the fragment, the `#` is part of the file name.
+        for (request, expected) in [
+            ("#internal", "src/internal.js"),
+            ("./src/x.svg#icon", "src/x.svg#icon"),
+            ("./src/x.svg?raw#icon", "src/x.svg?raw#icon"),
+            ("#internal.svg#frag", "src/internal.svg#frag"),
+            ("./src/icon#dark.svg", "src/icon#dark.svg"),
+            ("./src/icon#dark.svg?raw", "src/icon#dark.svg?raw"),
+        ] {
+            assert_eq!(
+                resolve_fixture("fragment", request, node_cjs_resolve_options).await?,
+                vec![expected],
+                "{request}"
+            );
+        }
+
+        // The query and the fragment are kept apart, so a query can be
+        // matched exactly.
+        let resolve_ident = |request: &str| {
+            let source = resolve(
+                fixtures_root().join("fragment".to_string()),
+                Value::new(ReferenceType::Undefined),
+                Request::parse_string(request.to_string()),
+                node_cjs_resolve_options(fixtures_root()),
+            )
+            .first_source();
+            async move {
+                let source = (*source.await?).expect("request should resolve");
+                source.ident().await
+            }
+        };
+        let ident = resolve_ident("./src/x.svg?raw#icon").await?;
+        assert_eq!(*ident.query.await?, "?raw");
+        assert_eq!(*ident.fragment.expect("fragment should be kept").await?, "icon");
+        let ident = resolve_ident("./src/icon#dark.svg").await?;
+        assert_eq!(ident.path.await?.path, "fragment/src/icon#dark.svg");
+        assert!(ident.fragment.is_none());
+    }
+}
+
+#[tokio::test]
+async fn style_resolve_options() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("style", "style-pkg", node_style_resolve_options).await?,
+            vec!["node_modules/style-pkg/src/index.scss"]
+        );
+        assert_eq!(
+            resolve_fixture("style", "style-pkg", node_cjs_resolve_options).await?,
+            vec!["node_modules/style-pkg/index.js"]
+        );
+        // Packages without an exports field fall back to the `style` field
+        // instead of `main`.
+        assert_eq!(
+            resolve_fixture("style", "legacy-style", node_style_resolve_options).await?,
+            vec!["node_modules/legacy-style/dist/legacy.css"]
+        );
+        assert_eq!(
+            resolve_fixture("style", "legacy-style", node_cjs_resolve_options).await?,
+            vec!["node_modules/legacy-style/index.js"]
+        );
+        assert_eq!(
+            resolve_fixture("style", "./src/theme", node_style_resolve_options).await?,
+            vec!["src/theme.sass"]
+        );
+        assert!(resolve_fixture("style", "./src/theme", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn mode_conditions() {
+    run! {
+        register();
+
+        let with_mode = |mode| {
+            let context = NodeResolveOptionsContext {
+                mode,
+                ..Default::default()
+            }
+            .cell();
+            move |root| node_cjs_resolve_options_with_context(root, context)
+        };
+        let development = with_mode(Some(ResolveMode::Development));
+        let production = with_mode(Some(ResolveMode::Production));
+        let none = with_mode(None);
+
+        assert_eq!(
+            resolve_fixture("mode", "dev-only", development).await?,
+            vec!["node_modules/dev-only/dev.js"]
+        );
+        assert_eq!(
+            resolve_fixture("mode", "dev-only", production).await?,
+            vec!["node_modules/dev-only/index.js"]
+        );
+        assert_eq!(
+            resolve_fixture("mode", "prod-only", production).await?,
+            vec!["node_modules/prod-only/prod.js"]
+        );
+        assert_eq!(
+            resolve_fixture("mode", "prod-only", development).await?,
+            vec!["node_modules/prod-only/index.js"]
+        );
+        for options in [development, production, none] {
+            assert_eq!(
+                resolve_fixture("mode", "default-only", options).await?,
+                vec!["node_modules/default-only/index.js"]
+            );
+        }
+        assert_eq!(
+            resolve_fixture("mode", "dev-only", none).await?,
+            vec!["node_modules/dev-only/index.js"]
+        );
+        assert_eq!(
+            resolve_fixture("mode", "prod-only", none).await?,
+            vec!["node_modules/prod-only/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn environment_conditions() {
+    run! {
+        register();
+
+        let with_environment = |conditions: Option<&[&str]>| {
+            let context = NodeResolveOptionsContext {
+                environment_conditions: conditions
+                    .map(|conditions| conditions.iter().map(|c| c.to_string()).collect()),
+                ..Default::default()
+            }
+            .cell();
+            move |root| node_cjs_resolve_options_with_context(root, context)
+        };
+
+        let main = with_environment(Some(&["electron", "node"]));
+        let renderer = with_environment(Some(&["electron-renderer", "electron"]));
+
+        assert_eq!(
+            resolve_fixture("electron", "electron-pkg", with_environment(None)).await?,
+            vec!["node_modules/electron-pkg/node.js"]
+        );
+        assert_eq!(
+            resolve_fixture("electron", "electron-pkg", main).await?,
+            vec!["node_modules/electron-pkg/electron.js"]
+        );
+        assert_eq!(
+            resolve_fixture("electron", "electron-pkg", renderer).await?,
+            vec!["node_modules/electron-pkg/renderer.js"]
+        );
+        // `node` is not implied by a custom set of conditions.
+        assert_eq!(
+            resolve_fixture("electron", "electron-pkg", with_environment(Some(&[]))).await?,
+            vec!["node_modules/electron-pkg/index.js"]
+        );
+    }
+}
+
+/// Returns the conditions of the exports and imports fields of `options`.
+async fn conditions(options: Vc<ResolveOptions>) -> Result<Vec<Vec<(String, ConditionValue)>>> {
+    let options = options.await?;
+    let into_package = options.into_package.iter().filter_map(|item| match item {
+        ResolveIntoPackage::ExportsField { conditions, .. } => Some(conditions),
+        ResolveIntoPackage::MainField { .. } | ResolveIntoPackage::MainFields { .. } => None,
+    });
+    let in_package = options.in_package.iter().filter_map(|item| match item {
+        ResolveInPackage::ImportsField { conditions, .. } => Some(conditions),
+        ResolveInPackage::AliasField(_) => None,
+    });
+    Ok(into_package
+        .chain(in_package)
+        .map(|conditions| {
+            conditions
+                .iter()
+                .map(|(condition, value)| (condition.clone(), *value))
+                .collect()
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn merge_extensions() {
+    run! {
+        register();
+
+        let base = node_esm_resolve_options(fixtures_root());
+        let merged = ResolveOptions::merge(
+            base,
+            ResolveOptionsPatch {
+                extensions: Some(vec![".ts".to_string()]),
+                ..Default::default()
+            }
+            .cell(),
+        );
+
+        assert_eq!(merged.await?.extensions, vec![".ts"]);
+        assert_eq!(conditions(merged).await?, conditions(base).await?);
+        assert_eq!(merged.await?.default_files, base.await?.default_files);
+    }
+}
+
+#[tokio::test]
+async fn merge_conditions() {
+    run! {
+        register();
+
+        let base = node_esm_resolve_options(fixtures_root());
+        let merged = ResolveOptions::merge(
+            base,
+            ResolveOptionsPatch {
+                conditions: Some(
+                    [
+                        ("import".to_string(), ConditionValue::Unknown),
+                        ("worker".to_string(), ConditionValue::Set),
+                    ]
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .cell(),
+        );
+
+        let expected = vec![
+            ("browser".to_string(), ConditionValue::Unset),
+            ("default".to_string(), ConditionValue::Set),
+            ("development".to_string(), ConditionValue::Unset),
+            ("import".to_string(), ConditionValue::Unknown),
+            ("node".to_string(), ConditionValue::Set),
+            ("production".to_string(), ConditionValue::Unset),
+            ("require".to_string(), ConditionValue::Negated),
+            ("types".to_string(), ConditionValue::Unset),
+            ("worker".to_string(), ConditionValue::Set),
+        ];
+        assert_eq!(conditions(merged).await?, vec![expected.clone(), expected]);
+        assert_eq!(merged.await?.extensions, base.await?.extensions);
+    }
+}
+
+#[tokio::test]
+async fn merge_conditions_set_wins() {
+    run! {
+        register();
+
+        let base = node_esm_resolve_options(fixtures_root());
+        let merged = ResolveOptions::merge(
+            base,
+            ResolveOptionsPatch {
+                conditions: Some(
+                    [
+                        ("browser".to_string(), ConditionValue::Set),
+                        ("node".to_string(), ConditionValue::Unset),
+                    ]
+                    .into(),
+                ),
+                ..Default::default()
+            }
+            .cell(),
+        );
+
+        // A condition which is set on either side stays set.
+        for conditions in conditions(merged).await? {
+            assert!(conditions.contains(&("browser".to_string(), ConditionValue::Set)));
+            assert!(conditions.contains(&("node".to_string(), ConditionValue::Set)));
+        }
+    }
+}
+
+#[tokio::test]
+async fn unspecified_conditions() {
+    run! {
+        register();
+
+        let with_unspecified_conditions = |unspecified_conditions| {
+            let context = NodeResolveOptionsContext {
+                unspecified_conditions: Some(unspecified_conditions),
+                ..Default::default()
+            }
+            .cell();
+            move |root| node_cjs_resolve_options_with_context(root, context)
+        };
+        let set = with_unspecified_conditions(ConditionValue::Set);
+        let unset = with_unspecified_conditions(ConditionValue::Unset);
+
+        assert_eq!(
+            resolve_fixture("unspecified-conditions", "custom-condition", set).await?,
+            vec!["node_modules/custom-condition/x.js"]
+        );
+        assert_eq!(
+            resolve_fixture("unspecified-conditions", "custom-condition", unset).await?,
+            vec!["node_modules/custom-condition/index.js"]
+        );
+        // Conditions known to the options are not affected.
+        for options in [set, unset] {
+            assert_eq!(
+                resolve_fixture("unspecified-conditions", "import-require", options).await?,
+                vec!["node_modules/import-require/require.js"]
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn negated_conditions() {
+    run! {
+        register();
+
+        use ConditionValue::{Negated, Set, Unset};
+
+        let with_conditions = |base: &[(&str, ConditionValue)], overrides: &[_]| {
+            let to_map = |conditions: &[(&str, ConditionValue)]| -> ResolutionConditions {
+                conditions
+                    .iter()
+                    .map(|(condition, value)| (condition.to_string(), *value))
+                    .collect()
+            };
+            let conditions = merge_resolution_conditions(&to_map(base), &to_map(overrides));
+            move |root| {
+                ResolveOptions {
+                    modules: vec![ResolveModules::Nested(root, vec!["node_modules".to_string()])],
+                    into_package: vec![ResolveIntoPackage::ExportsField {
+                        conditions,
+                        unspecified_conditions: Set,
+                    }],
+                    ..Default::default()
+                }
+                .cell()
+            }
+        };
+
+        assert_eq!(
+            resolve_fixture(
+                "exclusive-conditions",
+                "dual",
+                with_conditions(&[("import", Set), ("require", Negated)], &[])
+            )
+            .await?,
+            vec!["node_modules/dual/index.mjs"]
+        );
+        assert_eq!(
+            resolve_fixture(
+                "exclusive-conditions",
+                "dual",
+                with_conditions(&[("import", Negated), ("require", Set)], &[])
+            )
+            .await?,
+            vec!["node_modules/dual/index.cjs"]
+        );
+        // A negated condition stays off when it's set by overrides, while an
+        // unset one is switched on.
+        assert_eq!(
+            resolve_fixture(
+                "exclusive-conditions",
+                "dual",
+                with_conditions(&[("import", Negated), ("require", Set)], &[("import", Set)])
+            )
+            .await?,
+            vec!["node_modules/dual/index.cjs"]
+        );
+        assert_eq!(
+            resolve_fixture(
+                "exclusive-conditions",
+                "dual",
+                with_conditions(&[("import", Unset), ("require", Set)], &[("import", Set)])
+            )
+            .await?,
+            vec!["node_modules/dual/index.mjs"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn merge_negated_conditions() {
+    run! {
+        register();
+
+        use ConditionValue::{Negated, Set};
+
+        let with_patch = |root| {
+            let base = ResolveOptions {
+                modules: vec![ResolveModules::Nested(root, vec!["node_modules".to_string()])],
+                into_package: vec![ResolveIntoPackage::ExportsField {
+                    conditions: [("import".to_string(), Negated), ("require".to_string(), Set)]
+                        .into(),
+                    unspecified_conditions: Set,
+                }],
+                ..Default::default()
+            }
+            .cell();
+            ResolveOptions::merge(
+                base,
+                ResolveOptionsPatch {
+                    conditions: Some([("import".to_string(), Set)].into()),
+                    ..Default::default()
+                }
+                .cell(),
+            )
+        };
+
+        // The patch can't switch on a condition which is negated in the base.
+        assert_eq!(
+            resolve_fixture("exclusive-conditions", "dual", with_patch).await?,
+            vec!["node_modules/dual/index.cjs"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn merge_node_exclusive_conditions() {
+    run! {
+        register();
+
+        let with_patch =
+            |options: fn(Vc<FileSystemPath>) -> Vc<ResolveOptions>, condition: &str| {
+                let patch = ResolveOptionsPatch {
+                    conditions: Some([(condition.to_string(), ConditionValue::Set)].into()),
+                    ..Default::default()
+                }
+                .cell();
+                move |root| ResolveOptions::merge(options(root), patch)
+            };
+
+        // The patch can't switch on the condition of the other module kind.
+        let cjs = with_patch(node_cjs_resolve_options, "import");
+        assert_eq!(
+            resolve_fixture("exclusive-conditions", "dual", cjs).await?,
+            vec!["node_modules/dual/index.cjs"]
+        );
+        let esm = with_patch(node_esm_resolve_options, "require");
+        assert_eq!(
+            resolve_fixture("exclusive-conditions", "dual", esm).await?,
+            vec!["node_modules/dual/index.mjs"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn extension_alias() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            extension_alias: IndexMap::from([(
+                ".js".to_string(),
+                vec![".ts".to_string(), ".tsx".to_string(), ".js".to_string()],
+            )]),
+            ..Default::default()
+        }
+        .cell();
+        let with_alias = move |root| node_esm_resolve_options_with_context(root, context);
+
+        assert_eq!(
+            resolve_fixture("extension-alias", "./only-ts.js", with_alias).await?,
+            vec!["only-ts.ts"]
+        );
+        assert!(resolve_fixture("extension-alias", "./only-ts.js", node_esm_resolve_options)
+            .await?
+            .is_empty());
+        // The alias takes precedence over the file with the requested extension.
+        assert_eq!(
+            resolve_fixture("extension-alias", "./both.js", with_alias).await?,
+            vec!["both.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("extension-alias", "./both.js", node_esm_resolve_options).await?,
+            vec!["both.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn self_reference() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("self-reference/src", "@scope/self", node_cjs_resolve_options).await?,
+            vec!["index.js"]
+        );
+        assert_eq!(
+            resolve_fixture("self-reference/src", "@scope/self/feature", node_cjs_resolve_options)
+                .await?,
+            vec!["feature.js"]
+        );
+        assert!(resolve_fixture("self-reference/src", "@scope/other", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+    }
+}
+
+#[tokio::test]
+async fn strict_json_imports() {
+    run! {
+        register();
+
+        let with_strict_json_imports = |strict_json_imports: bool| {
+            move |root| {
+                ResolveOptions::merge(
+                    node_esm_resolve_options(root),
+                    ResolveOptionsPatch {
+                        strict_json_imports: Some(strict_json_imports),
+                        ..Default::default()
+                    }
+                    .cell(),
+                )
+            }
+        };
+        let import = ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import);
+
+        let issues = handle_fixture_issues(
+            "json-imports",
+            "./data.json",
+            import.clone(),
+            with_strict_json_imports(true),
+        )
+        .await?;
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        let (detail, has_source) = &issues[0];
+        assert!(detail.contains("`type: \"json\"` import attribute"), "{detail}");
+        assert!(has_source);
+
+        // Lenient mode, other modules, imports with attributes, dynamic
+        // `import()` and `require()` calls don't emit an issue.
+        let dynamic_import =
+            ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::DynamicImport);
+        for (request, reference_type, strict) in [
+            ("./data.json", import.clone(), false),
+            ("./code.js", import.clone(), true),
+            ("./data.json", ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes), true),
+            ("./data.json", dynamic_import, true),
+            ("./data.json", ReferenceType::CommonJs(CommonJsReferenceSubType::Require), true),
+        ] {
+            let issues = handle_fixture_issues(
+                "json-imports",
+                request,
+                reference_type,
+                with_strict_json_imports(strict),
+            )
+            .await?;
+            assert!(issues.is_empty(), "{request}: {issues:?}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn types_condition() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("types", "types-condition", node_ts_resolve_options).await?,
+            vec!["node_modules/types-condition/index.d.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("types", "types-condition", node_esm_resolve_options).await?,
+            vec!["node_modules/types-condition/index.mjs"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn types_field() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("types", "types-field", node_ts_resolve_options).await?,
+            vec!["node_modules/types-field/lib/index.d.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("types", "typings-field", node_ts_resolve_options).await?,
+            vec!["node_modules/typings-field/typings.d.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("types", "types-field", node_cjs_resolve_options).await?,
+            vec!["node_modules/types-field/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn typescript_extensions() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("typescript", "./util", node_ts_resolve_options).await?,
+            vec!["util.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("typescript", "./component", node_ts_resolve_options).await?,
+            vec!["component.tsx"]
+        );
+        assert_eq!(
+            resolve_fixture("typescript", "./declared", node_ts_resolve_options).await?,
+            vec!["declared.d.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("typescript", "./util", node_cjs_resolve_options).await?,
+            vec!["util.js"]
+        );
+        assert_eq!(
+            node_ts_resolve_options(fixtures_root()).await?.extensions,
+            vec![".ts", ".tsx", ".d.ts", ".js", ".jsx", ".mjs", ".json", ".node"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn typescript_default_files() {
+    run! {
+        register();
+
+        assert_eq!(
+            resolve_fixture("typescript", "./dir", node_ts_resolve_options).await?,
+            vec!["dir/index.ts"]
+        );
+        assert_eq!(
+            resolve_fixture("typescript", "./dir", node_cjs_resolve_options).await?,
+            vec!["dir/index.js"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn aliases() {
+    run! {
+        register();
+
+        let context = NodeResolveOptionsContext {
+            aliases: IndexMap::from([
+                ("@/".to_string(), "./alias/src/".to_string()),
+                ("@/config$".to_string(), "./alias/config.js".to_string()),
+                ("lib".to_string(), "./alias/lib".to_string()),
+                ("@shared/".to_string(), "./alias/shared/".to_string()),
+            ]),
+            ..Default::default()
+        }
+        .cell();
+        let options = |root| node_cjs_resolve_options_with_context(root, context);
+
+        // Prefix aliases.
+        assert_eq!(
+            resolve_fixture("alias", "@/util", options).await?,
+            vec!["src/util.js"]
+        );
+        // Exact aliases take precedence over prefix aliases.
+        assert_eq!(
+            resolve_fixture("alias", "@/config", options).await?,
+            vec!["config.js"]
+        );
+        // Aliases without a suffix match exactly and as a prefix, but only up to
+        // a `/`.
+        assert_eq!(
+            resolve_fixture("alias", "lib", options).await?,
+            vec!["lib/index.js"]
+        );
+        assert_eq!(
+            resolve_fixture("alias", "lib/get", options).await?,
+            vec!["lib/get.js"]
+        );
+        assert!(resolve_fixture("alias", "library", options).await?.is_empty());
+        assert!(resolve_fixture("alias", "@/util", node_cjs_resolve_options)
+            .await?
+            .is_empty());
+
+        // Aliases can point outside of the importing package.
+        let result = resolve(
+            fixtures_root().join("alias/app".to_string()),
+            Value::new(ReferenceType::Undefined),
+            Request::parse_string("@shared/shared".to_string()),
+            options(fixtures_root()),
+        )
+        .first_source()
+        .await?;
+        let Some(source) = *result else {
+            panic!("@shared/shared should resolve");
+        };
+        assert_eq!(
+            source.ident().path().await?.path,
+            "alias/shared/shared.js"
+        );
+    }
+}
+
+#[tokio::test]
+async fn package_json_invalidation() {
+    run! {
+        register();
+
+        let dir = tempfile::tempdir()?;
+        let package_dir = dir.path().join("node_modules/pkg");
+        std::fs::create_dir_all(&package_dir)?;
+        std::fs::create_dir_all(dir.path().join("src/nested"))?;
+        std::fs::write(package_dir.join("package.json"), r#"{ "main": "a.js" }"#)?;
+        std::fs::write(package_dir.join("a.js"), "")?;
+        std::fs::write(package_dir.join("b.js"), "")?;
+
+        let disk_fs = DiskFileSystem::new(
+            "temp".to_string(),
+            dir.path().to_string_lossy().to_string(),
+            vec![],
+        );
+        let fs: Vc<Box<dyn FileSystem>> = Vc::upcast(disk_fs);
+        let root = fs.root();
+        // Both lookups share the lookup of the package from the project root.
+        let results = ["src", "src/nested"].map(|path| {
+            resolve(
+                root.join(path.to_string()),
+                Value::new(ReferenceType::Undefined),
+                Request::parse_string("pkg".to_string()),
+                node_cjs_resolve_options(root),
+            )
+            .first_source()
+        });
+        for result in results {
+            let source = (*result.strongly_consistent().await?).expect("pkg should resolve");
+            assert_eq!(source.ident().path().await?.path, "node_modules/pkg/a.js");
+        }
+
+        root.join("node_modules/pkg/package.json".to_string())
+            .write(FileContent::Content(File::from(r#"{ "main": "b.js" }"#)).cell())
+            .await?;
+        for result in results {
+            let source = (*result.strongly_consistent().await?).expect("pkg should resolve");
+            assert_eq!(source.ident().path().await?.path, "node_modules/pkg/b.js");
+        }
+
+        // A closer node_modules directory which is created later takes
+        // precedence, and the root one is used again once it's deleted.
+        let closer_package_dir = dir.path().join("src/node_modules/pkg");
+        std::fs::create_dir_all(&closer_package_dir)?;
+        std::fs::write(closer_package_dir.join("package.json"), r#"{ "main": "c.js" }"#)?;
+        std::fs::write(closer_package_dir.join("c.js"), "")?;
+        disk_fs.await?.invalidate();
+        for result in results {
+            let source = (*result.strongly_consistent().await?).expect("pkg should resolve");
+            assert_eq!(source.ident().path().await?.path, "src/node_modules/pkg/c.js");
+        }
+
+        std::fs::remove_dir_all(dir.path().join("src/node_modules"))?;
+        disk_fs.await?.invalidate();
+        for result in results {
+            let source = (*result.strongly_consistent().await?).expect("pkg should resolve");
+            assert_eq!(source.ident().path().await?.path, "node_modules/pkg/b.js");
+        }
+
+        // Without any node_modules directory, the package can't be resolved.
+        std::fs::remove_dir_all(dir.path().join("node_modules"))?;
+        disk_fs.await?.invalidate();
+        for result in results {
+            assert!(result.strongly_consistent().await?.is_none());
+        }
+    }
+}
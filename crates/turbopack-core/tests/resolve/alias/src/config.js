@@ -0,0 +1 @@
+module.exports = "src/config";
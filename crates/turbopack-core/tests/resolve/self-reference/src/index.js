@@ -0,0 +1 @@
+module.exports = require("@scope/self/feature");
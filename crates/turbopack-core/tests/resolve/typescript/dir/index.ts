@@ -0,0 +1 @@
+export const index = "ts";
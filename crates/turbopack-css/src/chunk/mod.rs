@@ -22,7 +22,7 @@ use turbopack_core::{
     },
     module::Module,
     output::{OutputAsset, OutputAssets},
-    reference_type::ImportContext,
+    reference_type::{ImportContext, ImportLayer},
     server_fs::ServerFileSystem,
     source_map::{GenerateSourceMap, OptionSourceMap},
 };
@@ -81,8 +81,12 @@ impl CssChunk {
             let mut close: Vec<String> = vec![];
             if let Some(import_context) = content.import_context {
                 let import_context = &*import_context.await?;
-                if !&import_context.layers.is_empty() {
-                    writeln!(body, "@layer {} {{", import_context.layers.join("."))?;
+                for layer in import_context.layer_blocks() {
+                    match layer {
+                        ImportLayer::None => continue,
+                        ImportLayer::Anonymous => writeln!(body, "@layer {{")?,
+                        ImportLayer::Named(name) => writeln!(body, "@layer {} {{", name)?,
+                    }
                     close.push("}\n".to_owned());
                 }
                 if !&import_context.media.is_empty() {
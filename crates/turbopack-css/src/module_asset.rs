@@ -15,7 +15,7 @@ use turbopack_core::{
     issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
     module::Module,
     reference::{ModuleReference, ModuleReferences},
-    reference_type::{CssReferenceSubType, ReferenceType},
+    reference_type::{ComposeTarget, CssReferenceSubType, ReferenceType},
     resolve::{origin::ResolveOrigin, parse::Request},
     source::Source,
     source_map::OptionSourceMap,
@@ -178,6 +178,29 @@ impl ModuleCssAsset {
         } = &*result
         {
             for (class_name, export_class_names) in exports {
+                // All classes composed from the same module share a single
+                // reference, e.g. `composes: a b from "./other.css"`.
+                let mut composed_class_names: IndexMap<&str, Vec<String>> = IndexMap::new();
+                for export_class_name in &export_class_names.composes {
+                    if let CssModuleReference::Dependency { specifier, name } = export_class_name {
+                        composed_class_names
+                            .entry(specifier.as_str())
+                            .or_default()
+                            .push(name.to_string());
+                    }
+                }
+                let compose_references: IndexMap<&str, _> = composed_class_names
+                    .into_iter()
+                    .map(|(specifier, class_names)| {
+                        let reference = CssModuleComposeReference::new(
+                            Vc::upcast(self),
+                            Request::parse(Value::new(specifier.to_string().into())),
+                            ComposeTarget::new(class_names),
+                        );
+                        (specifier, reference)
+                    })
+                    .collect();
+
                 let mut export = Vec::default();
 
                 export.push(ModuleCssClass::Local {
@@ -189,10 +212,7 @@ impl ModuleCssAsset {
                         CssModuleReference::Dependency { specifier, name } => {
                             ModuleCssClass::Import {
                                 original: name.to_string(),
-                                from: CssModuleComposeReference::new(
-                                    Vc::upcast(self),
-                                    Request::parse(Value::new(specifier.to_string().into())),
-                                ),
+                                from: compose_references[specifier.as_str()],
                             }
                         }
                         CssModuleReference::Local { name } => ModuleCssClass::Local {
@@ -3,7 +3,7 @@ use turbo_tasks::{Value, ValueToString, Vc};
 use turbopack_core::{
     chunk::ChunkableModuleReference,
     reference::ModuleReference,
-    reference_type::CssReferenceSubType,
+    reference_type::{ComposeTarget, CssReferenceSubType},
     resolve::{origin::ResolveOrigin, parse::Request, ModuleResolveResult},
 };
 
@@ -15,30 +15,39 @@ use crate::references::css_resolve;
 pub struct CssModuleComposeReference {
     pub origin: Vc<Box<dyn ResolveOrigin>>,
     pub request: Vc<Request>,
+    pub target: Vc<ComposeTarget>,
 }
 
 #[turbo_tasks::value_impl]
 impl CssModuleComposeReference {
     /// Creates a new [`CssModuleComposeReference`].
     #[turbo_tasks::function]
-    pub fn new(origin: Vc<Box<dyn ResolveOrigin>>, request: Vc<Request>) -> Vc<Self> {
-        Self::cell(CssModuleComposeReference { origin, request })
+    pub fn new(
+        origin: Vc<Box<dyn ResolveOrigin>>,
+        request: Vc<Request>,
+        target: Vc<ComposeTarget>,
+    ) -> Vc<Self> {
+        Self::cell(CssModuleComposeReference {
+            origin,
+            request,
+            target,
+        })
     }
 }
 
 #[turbo_tasks::value_impl]
 impl ModuleReference for CssModuleComposeReference {
     #[turbo_tasks::function]
-    fn resolve_reference(&self) -> Vc<ModuleResolveResult> {
-        css_resolve(
+    async fn resolve_reference(&self) -> Result<Vc<ModuleResolveResult>> {
+        Ok(css_resolve(
             self.origin,
             self.request,
-            Value::new(CssReferenceSubType::Compose),
+            Value::new(CssReferenceSubType::compose(self.target).await?),
             // TODO: add real issue source, currently impossible because `CssClassName` doesn't
             // contain the source span
             // https://docs.rs/swc_css_modules/0.21.16/swc_css_modules/enum.CssClassName.html
             None,
-        )
+        ))
     }
 }
 
@@ -1,3 +1,5 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
 use anyhow::Result;
 use lightningcss::{
     media_query::MediaList,
@@ -12,12 +14,18 @@ use swc_core::{
     },
 };
 use turbo_tasks::{Value, ValueToString, Vc};
+use turbo_tasks_fs::FileSystemPath;
 use turbopack_core::{
     chunk::{ChunkableModuleReference, ChunkingContext},
     issue::IssueSource,
+    module::{Module, Modules},
     reference::ModuleReference,
-    reference_type::{CssReferenceSubType, ImportContext},
-    resolve::{origin::ResolveOrigin, parse::Request, ModuleResolveResult},
+    reference_type::{CssReferenceSubType, ImportContext, ImportLayer, ReferenceType},
+    resolve::{
+        origin::{ResolveOrigin, ResolveOriginExt},
+        parse::Request,
+        ModuleResolveResult,
+    },
 };
 
 use crate::{
@@ -28,9 +36,10 @@ use crate::{
 
 #[turbo_tasks::value(into = "new", eq = "manual", serialization = "none")]
 pub enum ImportAttributes {
+    /// `layer_name` is `Some(None)` for an anonymous layer.
     LightningCss {
         #[turbo_tasks(trace_ignore)]
-        layer_name: Option<LayerName<'static>>,
+        layer_name: Option<Option<LayerName<'static>>>,
         #[turbo_tasks(trace_ignore)]
         supports: Option<SupportsCondition<'static>>,
         #[turbo_tasks(trace_ignore)]
@@ -38,7 +47,7 @@ pub enum ImportAttributes {
     },
     Swc {
         #[turbo_tasks(trace_ignore)]
-        layer_name: Option<swc_core::css::ast::LayerName>,
+        layer_name: Option<Option<swc_core::css::ast::LayerName>>,
         #[turbo_tasks(trace_ignore)]
         supports: Option<swc_core::css::ast::SupportsCondition>,
         #[turbo_tasks(trace_ignore)]
@@ -54,7 +63,7 @@ impl PartialEq for ImportAttributes {
 
 impl ImportAttributes {
     pub fn new_from_lightningcss(prelude: &ImportRule<'static>) -> Self {
-        let layer_name = prelude.layer.clone().flatten();
+        let layer_name = prelude.layer.clone();
 
         let supports = prelude.supports.clone();
 
@@ -69,10 +78,7 @@ impl ImportAttributes {
 
     pub fn new_from_swc(prelude: &swc_core::css::ast::ImportPrelude) -> Self {
         let layer_name = prelude.layer_name.as_ref().map(|l| match l {
-            box swc_core::css::ast::ImportLayerName::Ident(_) => swc_core::css::ast::LayerName {
-                span: DUMMY_SP,
-                name: vec![],
-            },
+            box swc_core::css::ast::ImportLayerName::Ident(_) => None,
             box swc_core::css::ast::ImportLayerName::Function(f) => {
                 assert_eq!(f.value.len(), 1);
                 assert!(matches!(
@@ -80,7 +86,7 @@ impl ImportAttributes {
                     swc_core::css::ast::ComponentValue::LayerName(_)
                 ));
                 if let swc_core::css::ast::ComponentValue::LayerName(layer_name) = &f.value[0] {
-                    *layer_name.clone()
+                    Some(*layer_name.clone())
                 } else {
                     unreachable!()
                 }
@@ -148,9 +154,7 @@ impl ImportAttributes {
                 supports,
                 media,
             } => turbopack_core::reference_type::ImportAttributes {
-                layer: layer_name
-                    .as_ref()
-                    .map(|l| l.to_css_string(Default::default()).unwrap()),
+                layer: import_layer(layer_name, |l| l.to_css_string(Default::default()).unwrap()),
                 supports: supports
                     .as_ref()
                     .map(|s| s.to_css_string(Default::default()).unwrap()),
@@ -167,7 +171,7 @@ impl ImportAttributes {
                 supports,
                 media,
             } => turbopack_core::reference_type::ImportAttributes {
-                layer: layer_name.as_ref().map(gen_swc_node),
+                layer: import_layer(layer_name, gen_swc_node),
                 supports: supports.as_ref().map(gen_swc_node),
                 media: media
                     .as_ref()
@@ -177,6 +181,17 @@ impl ImportAttributes {
     }
 }
 
+fn import_layer<L>(
+    layer_name: &Option<Option<L>>,
+    to_string: impl Fn(&L) -> String,
+) -> ImportLayer {
+    match layer_name {
+        None => ImportLayer::None,
+        Some(None) => ImportLayer::Anonymous,
+        Some(Some(name)) => ImportLayer::Named(to_string(name)),
+    }
+}
+
 fn gen_swc_node<N>(node: N) -> String
 where
     N: Spanned,
@@ -226,21 +241,109 @@ impl ImportAssetReference {
 impl ModuleReference for ImportAssetReference {
     #[turbo_tasks::function]
     async fn resolve_reference(&self) -> Result<Vc<ModuleResolveResult>> {
-        let import_context = {
-            let own_attrs = (*self.attributes.await?).as_reference_import_attributes();
-            self.import_context
-                .add_attributes(own_attrs.layer, own_attrs.media, own_attrs.supports)
+        let own_attrs = (*self.attributes.await?).as_reference_import_attributes();
+        let has_conditions =
+            !own_attrs.layer.is_none() || own_attrs.media.is_some() || own_attrs.supports.is_some();
+        let import_context = self.import_context.add_attributes(
+            own_attrs.layer.clone(),
+            own_attrs.media.clone(),
+            own_attrs.supports.clone(),
+        );
+        let result = css_resolve(
+            self.origin,
+            self.request,
+            Value::new(CssReferenceSubType::at_import(import_context).await?),
+            Some(self.issue_source),
+        );
+        // Only imports with conditions can grow the context around an import
+        // cycle.
+        if !has_conditions {
+            return Ok(result);
+        }
+        let Some(stylesheet) = *result.first_module().await? else {
+            return Ok(result);
         };
 
+        let importers = import_chain(stylesheet, self.origin.origin_path())
+            .await?
+            .clone_value();
+        let chained_context = self.import_context.add_attributes_in_chain(
+            importers,
+            stylesheet.ident().path(),
+            own_attrs.layer,
+            own_attrs.media,
+            own_attrs.supports,
+        );
+        if chained_context.resolve().await? == import_context.resolve().await? {
+            return Ok(result);
+        }
+
+        // The import closes a cycle, so its conditions are ignored.
         Ok(css_resolve(
             self.origin,
             self.request,
-            Value::new(CssReferenceSubType::AtImport(Some(import_context))),
+            Value::new(CssReferenceSubType::at_import(chained_context).await?),
             Some(self.issue_source),
         ))
     }
 }
 
+/// Returns the stylesheets which `module` imports with `@import`, without
+/// their conditions.
+#[turbo_tasks::function]
+async fn imported_stylesheets(module: Vc<Box<dyn Module>>) -> Result<Vc<Modules>> {
+    let reference_type = Value::new(ReferenceType::Css(CssReferenceSubType::AtImport(None)));
+    let mut stylesheets = vec![];
+    for &reference in module.references().await?.iter() {
+        let Some(import) = Vc::try_resolve_downcast_type::<ImportAssetReference>(reference).await?
+        else {
+            continue;
+        };
+        let import = import.await?;
+        let result = import.origin.resolve_asset(
+            import.request,
+            import.origin.resolve_options(reference_type.clone()),
+            reference_type.clone(),
+        );
+        stylesheets.extend(result.primary_modules().await?.iter().copied());
+    }
+    Ok(Vc::cell(stylesheets))
+}
+
+/// Returns the stylesheets of the import chain from `stylesheet` to
+/// `importer`, outermost first, when `stylesheet` imports `importer`, directly
+/// or through other stylesheets. Otherwise, it's only `importer`.
+#[turbo_tasks::function]
+async fn import_chain(
+    stylesheet: Vc<Box<dyn Module>>,
+    importer: Vc<FileSystemPath>,
+) -> Result<Vc<Vec<Vc<FileSystemPath>>>> {
+    let importer_value = importer.await?;
+    let mut parents: HashMap<Vc<FileSystemPath>, Option<Vc<FileSystemPath>>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    let path = stylesheet.ident().path().resolve().await?;
+    parents.insert(path, None);
+    queue.push_back((stylesheet, path));
+    while let Some((module, path)) = queue.pop_front() {
+        if *path.await? == *importer_value {
+            let mut chain = vec![path];
+            while let Some(Some(parent)) = parents.get(chain.last().unwrap()) {
+                chain.push(*parent);
+            }
+            chain.reverse();
+            return Ok(Vc::cell(chain));
+        }
+        for &imported in imported_stylesheets(module).await?.iter() {
+            let imported_path = imported.ident().path().resolve().await?;
+            if let Entry::Vacant(entry) = parents.entry(imported_path) {
+                entry.insert(Some(path));
+                queue.push_back((imported, imported_path));
+            }
+        }
+    }
+    Ok(Vc::cell(vec![importer]))
+}
+
 #[turbo_tasks::value_impl]
 impl ValueToString for ImportAssetReference {
     #[turbo_tasks::function]
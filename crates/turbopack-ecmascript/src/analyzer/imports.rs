@@ -30,6 +30,9 @@ static ANNOTATION_TRANSITION: Lazy<JsWord> = Lazy::new(|| "transition".into());
 /// Changes the chunking type for the annotated import
 static ANNOTATION_CHUNKING_TYPE: Lazy<JsWord> = Lazy::new(|| "chunking-type".into());
 
+/// The module type requested by the `type` import attribute
+static ATTRIBUTE_MODULE_TYPE: Lazy<JsWord> = Lazy::new(|| "type".into());
+
 impl ImportAnnotations {
     fn insert(&mut self, key: JsWord, value: Option<JsWord>) {
         self.map.insert(key, value);
@@ -52,6 +55,40 @@ impl ImportAnnotations {
             .get(&ANNOTATION_CHUNKING_TYPE)
             .and_then(|w| w.as_ref().map(|w| &**w))
     }
+
+    /// Adds the `type` attribute of an import's attributes, e.g. `with {
+    /// type: "json" }`. Other attributes are ignored.
+    pub fn insert_import_attributes(&mut self, with: Option<&ObjectLit>) {
+        let Some(with) = with else {
+            return;
+        };
+        for prop in &with.props {
+            let PropOrSpread::Prop(prop) = prop else {
+                continue;
+            };
+            let Prop::KeyValue(KeyValueProp { key, value }) = &**prop else {
+                continue;
+            };
+            let key = match key {
+                PropName::Ident(ident) => &ident.sym,
+                PropName::Str(str) => &str.value,
+                _ => continue,
+            };
+            if *key != *ATTRIBUTE_MODULE_TYPE {
+                continue;
+            }
+            if let Expr::Lit(Lit::Str(str)) = &**value {
+                self.insert(key.clone(), Some(str.value.clone()));
+            }
+        }
+    }
+
+    /// Returns the module type requested by the `type` import attribute
+    pub fn module_type(&self) -> Option<&str> {
+        self.map
+            .get(&ATTRIBUTE_MODULE_TYPE)
+            .and_then(|w| w.as_ref().map(|w| &**w))
+    }
 }
 
 impl Display for ImportAnnotations {
@@ -257,7 +294,8 @@ impl Visit for Analyzer<'_> {
     }
 
     fn visit_import_decl(&mut self, import: &ImportDecl) {
-        let annotations = take(&mut self.current_annotations);
+        let mut annotations = take(&mut self.current_annotations);
+        annotations.insert_import_attributes(import.with.as_deref());
         self.ensure_reference(
             import.span,
             import.src.value.clone(),
@@ -295,7 +333,8 @@ impl Visit for Analyzer<'_> {
     fn visit_export_all(&mut self, export: &ExportAll) {
         self.data.has_exports = true;
 
-        let annotations = take(&mut self.current_annotations);
+        let mut annotations = take(&mut self.current_annotations);
+        annotations.insert_import_attributes(export.with.as_deref());
         self.ensure_reference(
             export.span,
             export.src.value.clone(),
@@ -314,7 +353,8 @@ impl Visit for Analyzer<'_> {
     fn visit_named_export(&mut self, export: &NamedExport) {
         self.data.has_exports = true;
         if let Some(ref src) = export.src {
-            let annotations = take(&mut self.current_annotations);
+            let mut annotations = take(&mut self.current_annotations);
+            annotations.insert_import_attributes(export.with.as_deref());
 
             self.ensure_reference(
                 export.span,
@@ -528,7 +528,7 @@ impl Module for EcmascriptModuleAsset {
     async fn ident(&self) -> Result<Vc<AssetIdent>> {
         if let Some(inner_assets) = self.inner_assets {
             let mut ident = self.source.ident().await?.clone_value();
-            for (name, asset) in inner_assets.await?.iter() {
+            for (name, asset) in inner_assets.sorted().await?.iter() {
                 ident.add_asset(Vc::cell(name.clone()), asset.ident());
             }
             ident.add_modifier(modifier());
@@ -17,6 +17,7 @@ use turbopack_core::{
     chunk::ChunkableModuleReference,
     issue::IssueSource,
     reference::ModuleReference,
+    reference_type::CommonJsReferenceSubType,
     resolve::{origin::ResolveOrigin, parse::Request, ModuleResolveResult},
 };
 use turbopack_resolve::ecmascript::{cjs_resolve, try_to_severity};
@@ -63,6 +64,7 @@ impl ModuleReference for AmdDefineAssetReference {
         cjs_resolve(
             self.origin,
             self.request,
+            Value::new(CommonJsReferenceSubType::Undefined),
             Some(self.issue_source),
             try_to_severity(self.in_try),
         )
@@ -159,6 +161,7 @@ impl CodeGenerateable for AmdDefineWithDependenciesCodeGen {
                             cjs_resolve(
                                 self.origin,
                                 *request,
+                                Value::new(CommonJsReferenceSubType::Undefined),
                                 Some(self.issue_source),
                                 try_to_severity(self.in_try),
                             ),
@@ -9,6 +9,7 @@ use turbopack_core::{
     chunk::ChunkableModuleReference,
     issue::IssueSource,
     reference::ModuleReference,
+    reference_type::CommonJsReferenceSubType,
     resolve::{origin::ResolveOrigin, parse::Request, ModuleResolveResult},
 };
 use turbopack_resolve::ecmascript::{cjs_resolve, try_to_severity};
@@ -55,6 +56,7 @@ impl ModuleReference for CjsAssetReference {
         cjs_resolve(
             self.origin,
             self.request,
+            Value::new(CommonJsReferenceSubType::Require),
             Some(self.issue_source),
             try_to_severity(self.in_try),
         )
@@ -112,6 +114,7 @@ impl ModuleReference for CjsRequireAssetReference {
         cjs_resolve(
             self.origin,
             self.request,
+            Value::new(CommonJsReferenceSubType::Require),
             Some(self.issue_source),
             try_to_severity(self.in_try),
         )
@@ -146,6 +149,7 @@ impl CodeGenerateable for CjsRequireAssetReference {
             cjs_resolve(
                 self.origin,
                 self.request,
+                Value::new(CommonJsReferenceSubType::Require),
                 Some(self.issue_source),
                 try_to_severity(self.in_try),
             ),
@@ -220,6 +224,7 @@ impl ModuleReference for CjsRequireResolveAssetReference {
         cjs_resolve(
             self.origin,
             self.request,
+            Value::new(CommonJsReferenceSubType::RequireResolve),
             Some(self.issue_source),
             try_to_severity(self.in_try),
         )
@@ -254,6 +259,7 @@ impl CodeGenerateable for CjsRequireResolveAssetReference {
             cjs_resolve(
                 self.origin,
                 self.request,
+                Value::new(CommonJsReferenceSubType::RequireResolve),
                 Some(self.issue_source),
                 try_to_severity(self.in_try),
             ),
@@ -14,14 +14,14 @@ use turbopack_core::{
     issue::{IssueSeverity, IssueSource},
     module::Module,
     reference::ModuleReference,
-    reference_type::EcmaScriptModulesReferenceSubType,
+    reference_type::{EcmaScriptModulesReferenceSubType, JsonReferenceSubType},
     resolve::{
         origin::{ResolveOrigin, ResolveOriginExt},
         parse::Request,
         ExternalType, ModulePart, ModuleResolveResult, ModuleResolveResultItem,
     },
 };
-use turbopack_resolve::ecmascript::esm_resolve;
+use turbopack_resolve::ecmascript::{esm_json_resolve, esm_resolve};
 
 use crate::{
     analyzer::imports::ImportAnnotations,
@@ -142,6 +142,21 @@ impl EsmAssetReference {
 impl ModuleReference for EsmAssetReference {
     #[turbo_tasks::function]
     async fn resolve_reference(&self) -> Result<Vc<ModuleResolveResult>> {
+        if self.annotations.module_type() == Some("json") {
+            let ty = Value::new(match &self.export_name {
+                Some(part) => JsonReferenceSubType::ImportPartWithAttributes(*part),
+                None => JsonReferenceSubType::ImportWithAttributes,
+            });
+
+            return Ok(esm_json_resolve(
+                self.get_origin().resolve().await?,
+                self.request,
+                ty,
+                IssueSeverity::Error.cell(),
+                self.issue_source,
+            ));
+        }
+
         let ty = Value::new(match &self.export_name {
             Some(part) => EcmaScriptModulesReferenceSubType::ImportPart(*part),
             None => EcmaScriptModulesReferenceSubType::Import,
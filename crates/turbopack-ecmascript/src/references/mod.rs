@@ -2302,7 +2302,13 @@ async fn require_resolve_visitor(
     Ok(if args.len() == 1 {
         let pat = js_value_to_pattern(&args[0]);
         let request = Request::parse(Value::new(pat.clone()));
-        let resolved = cjs_resolve(origin, request, None, try_to_severity(in_try));
+        let resolved = cjs_resolve(
+            origin,
+            request,
+            Value::new(CommonJsReferenceSubType::RequireResolve),
+            None,
+            try_to_severity(in_try),
+        );
         let mut values = resolved
             .primary_modules()
             .await?
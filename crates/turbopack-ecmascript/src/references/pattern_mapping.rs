@@ -30,7 +30,7 @@ pub(crate) enum SinglePatternMapping {
     Invalid,
     /// Unresolveable request.
     Unresolveable(String),
-    /// Ignored request.
+    /// Ignored request, or a request for an empty module.
     Ignored,
     /// Constant request that always maps to the same module.
     ///
@@ -316,7 +316,9 @@ async fn to_single_pattern_mapping(
         ModuleResolveResultItem::External(s, ty) => {
             return Ok(SinglePatternMapping::External(s.clone(), *ty));
         }
-        ModuleResolveResultItem::Ignore => return Ok(SinglePatternMapping::Ignored),
+        ModuleResolveResultItem::Ignore | ModuleResolveResultItem::Empty => {
+            return Ok(SinglePatternMapping::Ignored);
+        }
         _ => {
             // TODO implement mapping
             CodeGenerationIssue {
@@ -25,6 +25,7 @@ use turbopack_core::{
     issue::{IssueSeverity, IssueSource},
     module::Module,
     reference::{ModuleReference, ModuleReferences},
+    reference_type::CommonJsReferenceSubType,
     resolve::{origin::ResolveOrigin, parse::Request, ModuleResolveResult},
     source::Source,
 };
@@ -177,7 +178,13 @@ impl RequireContextMap {
         for (context_relative, path) in list {
             if let Some(origin_relative) = origin_path.get_relative_path_to(&*path.await?) {
                 let request = Request::parse(Value::new(origin_relative.clone().into()));
-                let result = cjs_resolve(origin, request, issue_source, issue_severity);
+                let result = cjs_resolve(
+                    origin,
+                    request,
+                    Value::new(CommonJsReferenceSubType::Undefined),
+                    issue_source,
+                    issue_severity,
+                );
 
                 map.insert(
                     context_relative.clone(),
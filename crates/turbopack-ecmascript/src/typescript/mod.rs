@@ -180,7 +180,13 @@ impl CompilerReference {
 impl ModuleReference for CompilerReference {
     #[turbo_tasks::function]
     fn resolve_reference(&self) -> Vc<ModuleResolveResult> {
-        cjs_resolve(self.origin, self.request, None, IssueSeverity::Error.cell())
+        cjs_resolve(
+            self.origin,
+            self.request,
+            Value::new(CommonJsReferenceSubType::Undefined),
+            None,
+            IssueSeverity::Error.cell(),
+        )
     }
 }
 
@@ -247,7 +253,13 @@ impl TsNodeRequireReference {
 impl ModuleReference for TsNodeRequireReference {
     #[turbo_tasks::function]
     fn resolve_reference(&self) -> Vc<ModuleResolveResult> {
-        cjs_resolve(self.origin, self.request, None, IssueSeverity::Error.cell())
+        cjs_resolve(
+            self.origin,
+            self.request,
+            Value::new(CommonJsReferenceSubType::Undefined),
+            None,
+            IssueSeverity::Error.cell(),
+        )
     }
 }
 
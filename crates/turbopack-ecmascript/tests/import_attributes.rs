@@ -0,0 +1,187 @@
+#![cfg(test)]
+
+use anyhow::{anyhow, bail, Result};
+use swc_core::{
+    common::{FileName, SourceMap},
+    ecma::{
+        ast::{EsVersion, ModuleDecl, ModuleItem},
+        parser::{parse_file_as_module, EsConfig, Syntax},
+    },
+};
+use turbo_tasks::{Value, Vc};
+use turbo_tasks_fs::{FileSystem, FileSystemPath, VirtualFileSystem};
+use turbo_tasks_testing::{register, run};
+use turbopack_core::{
+    compile_time_info::CompileTimeInfo,
+    context::{AssetContext, ProcessResult},
+    reference::ModuleReference,
+    reference_type::{EcmaScriptModulesReferenceSubType, JsonReferenceSubType, ReferenceType},
+    resolve::{
+        options::ResolveOptions, origin::PlainResolveOrigin, parse::Request, ExternalType,
+        ModulePart, ModuleResolveResult, ModuleResolveResultItem, RequestKey, ResolveResult,
+    },
+    source::Source,
+};
+use turbopack_ecmascript::{
+    analyzer::imports::ImportAnnotations, references::esm::EsmAssetReference, register,
+};
+
+register!();
+
+/// An [AssetContext] which resolves every request to an external named after
+/// the reference type it was resolved with.
+#[turbo_tasks::value]
+struct ReferenceTypeAssetContext;
+
+#[turbo_tasks::value_impl]
+impl AssetContext for ReferenceTypeAssetContext {
+    #[turbo_tasks::function]
+    fn compile_time_info(&self) -> Result<Vc<CompileTimeInfo>> {
+        bail!("not needed for resolving")
+    }
+
+    #[turbo_tasks::function]
+    fn layer(&self) -> Vc<String> {
+        Vc::cell("test".to_string())
+    }
+
+    #[turbo_tasks::function]
+    fn resolve_options(
+        &self,
+        _origin_path: Vc<FileSystemPath>,
+        _reference_type: Value<ReferenceType>,
+    ) -> Vc<ResolveOptions> {
+        ResolveOptions::default().cell()
+    }
+
+    #[turbo_tasks::function]
+    fn resolve_asset(
+        &self,
+        _origin_path: Vc<FileSystemPath>,
+        _request: Vc<Request>,
+        _resolve_options: Vc<ResolveOptions>,
+        reference_type: Value<ReferenceType>,
+    ) -> Vc<ModuleResolveResult> {
+        ModuleResolveResult {
+            primary: [(
+                RequestKey::default(),
+                ModuleResolveResultItem::External(
+                    reference_type.into_value().to_string(),
+                    ExternalType::EcmaScriptModule,
+                ),
+            )]
+            .into_iter()
+            .collect(),
+            affecting_sources: Vec::new(),
+        }
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn process(
+        &self,
+        _asset: Vc<Box<dyn Source>>,
+        _reference_type: Value<ReferenceType>,
+    ) -> Result<Vc<ProcessResult>> {
+        bail!("not needed for resolving")
+    }
+
+    #[turbo_tasks::function]
+    fn process_resolve_result(
+        &self,
+        _result: Vc<ResolveResult>,
+        _reference_type: Value<ReferenceType>,
+    ) -> Result<Vc<ModuleResolveResult>> {
+        bail!("not needed for resolving")
+    }
+
+    #[turbo_tasks::function]
+    fn with_transition(&self, _transition: String) -> Result<Vc<Box<dyn AssetContext>>> {
+        bail!("not needed for resolving")
+    }
+}
+
+/// Creates the [EsmAssetReference] for the single import in `source`, the way
+/// the module analysis does.
+fn esm_reference(source: &str) -> Result<Vc<EsmAssetReference>> {
+    esm_reference_with_export_name(source, None)
+}
+
+/// Like [esm_reference], but for a part of the importing module.
+fn esm_reference_with_export_name(
+    source: &str,
+    export_name: Option<Vc<ModulePart>>,
+) -> Result<Vc<EsmAssetReference>> {
+    let cm = SourceMap::default();
+    let fm = cm.new_source_file(FileName::Anon, source.to_string());
+    let syntax = Syntax::Es(EsConfig {
+        import_attributes: true,
+        ..Default::default()
+    });
+    let module = parse_file_as_module(&fm, syntax, EsVersion::latest(), None, &mut vec![])
+        .map_err(|err| anyhow!("failed to parse {source}: {err:?}"))?;
+    let Some(ModuleItem::ModuleDecl(ModuleDecl::Import(import))) = module.body.first() else {
+        bail!("expected an import in {source}");
+    };
+
+    let mut annotations = ImportAnnotations::default();
+    annotations.insert_import_attributes(import.with.as_deref());
+
+    let fs = VirtualFileSystem::new();
+    let origin = PlainResolveOrigin::new(
+        Vc::upcast(ReferenceTypeAssetContext.cell()),
+        fs.root().join("index.js".to_string()),
+    );
+    Ok(EsmAssetReference::new(
+        Vc::upcast(origin),
+        Request::parse_string(import.src.value.to_string()),
+        None,
+        Value::new(annotations),
+        export_name,
+        false,
+    ))
+}
+
+/// Returns the reference type the reference was resolved with.
+async fn resolved_reference_type(reference: Vc<EsmAssetReference>) -> Result<String> {
+    let result = reference.resolve_reference().await?;
+    match result.primary.values().next() {
+        Some(ModuleResolveResultItem::External(reference_type, _)) => Ok(reference_type.clone()),
+        item => bail!("expected an external, got {item:?}"),
+    }
+}
+
+#[tokio::test]
+async fn json_import_attribute() {
+    run! {
+        register();
+
+        let json = ReferenceType::Json(JsonReferenceSubType::ImportWithAttributes).to_string();
+        let esm = ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import)
+            .to_string();
+
+        let with = esm_reference(r#"import data from "./data.json" with { type: "json" };"#)?;
+        assert_eq!(resolved_reference_type(with).await?, json);
+
+        let assert = esm_reference(r#"import data from "./data.json" assert { type: "json" };"#)?;
+        assert_eq!(resolved_reference_type(assert).await?, json);
+
+        let quoted = esm_reference(r#"import data from "./data.json" with { "type": "json" };"#)?;
+        assert_eq!(resolved_reference_type(quoted).await?, json);
+
+        let plain = esm_reference(r#"import data from "./data.json";"#)?;
+        assert_eq!(resolved_reference_type(plain).await?, esm);
+
+        let other = esm_reference(r#"import styles from "./styles.css" with { type: "css" };"#)?;
+        assert_eq!(resolved_reference_type(other).await?, esm);
+
+        // The part of the importing module is kept.
+        let part = ModulePart::export("data".to_string()).resolve().await?;
+        let source = r#"import data from "./data.json" with { type: "json" };"#;
+        let with_part = esm_reference_with_export_name(source, Some(part))?;
+        assert_eq!(
+            resolved_reference_type(with_part).await?,
+            ReferenceType::Json(JsonReferenceSubType::ImportPartWithAttributes(part)).to_string()
+        );
+    }
+}
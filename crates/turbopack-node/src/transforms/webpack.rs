@@ -593,7 +593,9 @@ async fn apply_webpack_resolve_options(
         resolve_options.into_package.retain(|item| {
             !matches!(
                 item,
-                ResolveIntoPackage::ExportsField { .. } | ResolveIntoPackage::MainField { .. }
+                ResolveIntoPackage::ExportsField { .. }
+                    | ResolveIntoPackage::MainField { .. }
+                    | ResolveIntoPackage::MainFields { .. }
             )
         });
     }
@@ -606,7 +608,12 @@ async fn apply_webpack_resolve_options(
     if let Some(main_fields) = webpack_resolve_options.main_fields {
         let mut old = resolve_options
             .into_package
-            .extract_if(|field| matches!(field, ResolveIntoPackage::MainField { .. }))
+            .extract_if(|field| {
+                matches!(
+                    field,
+                    ResolveIntoPackage::MainField { .. } | ResolveIntoPackage::MainFields { .. }
+                )
+            })
             .collect::<Vec<_>>();
         for field in main_fields {
             if field == "..." {
@@ -2,7 +2,10 @@ use anyhow::Result;
 use turbo_tasks::{Value, Vc};
 use turbopack_core::{
     issue::{IssueSeverity, IssueSource},
-    reference_type::{CommonJsReferenceSubType, EcmaScriptModulesReferenceSubType, ReferenceType},
+    reference_type::{
+        CommonJsReferenceSubType, EcmaScriptModulesReferenceSubType, JsonReferenceSubType,
+        ReferenceType,
+    },
     resolve::{
         handle_resolve_error,
         options::{
@@ -47,7 +50,7 @@ pub async fn apply_esm_specific_options(options: Vc<ResolveOptions>) -> Result<V
     // options.fully_specified = true;
     for conditions in get_condition_maps(&mut options) {
         conditions.insert("import".to_string(), ConditionValue::Set);
-        conditions.insert("require".to_string(), ConditionValue::Unset);
+        conditions.insert("require".to_string(), ConditionValue::Negated);
     }
     Ok(options.into())
 }
@@ -56,7 +59,7 @@ pub async fn apply_esm_specific_options(options: Vc<ResolveOptions>) -> Result<V
 pub async fn apply_cjs_specific_options(options: Vc<ResolveOptions>) -> Result<Vc<ResolveOptions>> {
     let mut options: ResolveOptions = options.await?.clone_value();
     for conditions in get_condition_maps(&mut options) {
-        conditions.insert("import".to_string(), ConditionValue::Unset);
+        conditions.insert("import".to_string(), ConditionValue::Negated);
         conditions.insert("require".to_string(), ConditionValue::Set);
     }
     Ok(options.into())
@@ -77,15 +80,32 @@ pub async fn esm_resolve(
     specific_resolve(origin, request, options, ty, issue_severity, issue_source).await
 }
 
+/// Resolves an ES module import with a `type: "json"` import attribute. It's
+/// resolved with the ESM conditions, but as a JSON reference.
+#[turbo_tasks::function]
+pub async fn esm_json_resolve(
+    origin: Vc<Box<dyn ResolveOrigin>>,
+    request: Vc<Request>,
+    ty: Value<JsonReferenceSubType>,
+    issue_severity: Vc<IssueSeverity>,
+    issue_source: Option<Vc<IssueSource>>,
+) -> Result<Vc<ModuleResolveResult>> {
+    let ty = Value::new(ReferenceType::Json(ty.into_value()));
+    let options = apply_esm_specific_options(origin.resolve_options(ty.clone()))
+        .resolve()
+        .await?;
+    specific_resolve(origin, request, options, ty, issue_severity, issue_source).await
+}
+
 #[turbo_tasks::function]
 pub async fn cjs_resolve(
     origin: Vc<Box<dyn ResolveOrigin>>,
     request: Vc<Request>,
+    ty: Value<CommonJsReferenceSubType>,
     issue_source: Option<Vc<IssueSource>>,
     issue_severity: Vc<IssueSeverity>,
 ) -> Result<Vc<ModuleResolveResult>> {
-    // TODO pass CommonJsReferenceSubType
-    let ty = Value::new(ReferenceType::CommonJs(CommonJsReferenceSubType::Undefined));
+    let ty = Value::new(ReferenceType::CommonJs(ty.into_value()));
     let options = apply_cjs_specific_options(origin.resolve_options(ty.clone()))
         .resolve()
         .await?;
@@ -371,9 +371,7 @@ pub async fn type_resolve(
     let options = origin.resolve_options(ty.clone());
     let options = apply_typescript_types_options(options);
     let types_request = if let Request::Module {
-        module: m,
-        path: p,
-        query: _,
+        module: m, path: p, ..
     } = &*request.await?
     {
         let m = if let Some(stripped) = m.strip_prefix('@') {
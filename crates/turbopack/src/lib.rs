@@ -42,9 +42,7 @@ use turbopack_core::{
     module::Module,
     output::OutputAsset,
     raw_module::RawModule,
-    reference_type::{
-        CssReferenceSubType, EcmaScriptModulesReferenceSubType, InnerAssets, ReferenceType,
-    },
+    reference_type::{InnerAssets, ReferenceType},
     resolve::{
         options::ResolveOptions, origin::PlainResolveOrigin, parse::Request, resolve, ModulePart,
         ModuleResolveResult, ModuleResolveResultItem, ResolveResult,
@@ -232,10 +230,8 @@ async fn apply_module_type(
             Vc::upcast(module_asset_context),
             *ty,
             *use_swc_css,
-            if let ReferenceType::Css(CssReferenceSubType::AtImport(import)) =
-                reference_type.into_value()
-            {
-                import
+            if let ReferenceType::Css(sub_type) = reference_type.into_value() {
+                sub_type.import_context()
             } else {
                 None
             },
@@ -430,16 +426,8 @@ async fn process_default_internal(
     );
 
     let reference_type = reference_type.into_value();
-    let part: Option<Vc<ModulePart>> = match &reference_type {
-        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportPart(part)) => {
-            Some(*part)
-        }
-        _ => None,
-    };
-    let inner_assets = match &reference_type {
-        ReferenceType::Internal(inner_assets) => Some(*inner_assets),
-        _ => None,
-    };
+    let part = reference_type.as_module_part();
+    let inner_assets = reference_type.inner_assets();
     let mut current_source = source;
     let mut current_module_type = None;
     for (i, rule) in options.await?.rules.iter().enumerate() {
@@ -11,7 +11,9 @@ pub use rule_condition::*;
 use turbo_tasks::Vc;
 use turbo_tasks_fs::{glob::Glob, FileSystemPath};
 use turbopack_core::{
-    reference_type::{CssReferenceSubType, ReferenceType, UrlReferenceSubType},
+    reference_type::{
+        CssReferenceSubType, JsonReferenceSubType, ReferenceType, UrlReferenceSubType,
+    },
     resolve::options::{ImportMap, ImportMapping},
 };
 use turbopack_css::CssModuleAssetType;
@@ -365,6 +367,14 @@ impl ModuleOptions {
                 )),
                 vec![ModuleRuleEffect::ModuleType(ModuleType::Static)],
             ),
+            // Imports with a `type: "json"` attribute are JSON, regardless of
+            // the extension of the file.
+            ModuleRule::new(
+                ModuleRuleCondition::ReferenceType(ReferenceType::Json(
+                    JsonReferenceSubType::Undefined,
+                )),
+                vec![ModuleRuleEffect::ModuleType(ModuleType::Json)],
+            ),
         ];
 
         if enable_raw_css {
@@ -0,0 +1,125 @@
+#![cfg(test)]
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use turbo_tasks::{TurboTasks, Value, ValueToString, Vc};
+use turbo_tasks_fs::{DiskFileSystem, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack::{module_options::ModuleOptionsContext, register, ModuleAssetContext};
+use turbopack_core::{
+    compile_time_info::CompileTimeInfo,
+    context::AssetContext,
+    environment::{Environment, ExecutionEnvironment, NodeJsEnvironment},
+    file_source::FileSource,
+    issue::IssueDescriptionExt,
+    module::Module,
+    reference::ModuleReference,
+    reference_type::{CssReferenceSubType, ReferenceType},
+};
+use turbopack_resolve::resolve_options_context::ResolveOptionsContext;
+
+/// The most modules a fixture can have. A larger graph means the traversal
+/// didn't end.
+const MAX_MODULES: usize = 16;
+
+/// Returns the idents of all modules reachable from the `a.css` stylesheet of
+/// `fixture`, following its `@import`s.
+#[turbo_tasks::function]
+async fn import_graph(fixture: String) -> Result<Vc<Vec<String>>> {
+    let root = DiskFileSystem::new(
+        "fixtures".to_string(),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/css-import-cycles").to_string(),
+        vec![],
+    )
+    .root();
+    let module_asset_context = ModuleAssetContext::new(
+        Vc::cell(HashMap::new()),
+        CompileTimeInfo::new(Environment::new(Value::new(
+            ExecutionEnvironment::NodeJsLambda(NodeJsEnvironment::default().into()),
+        ))),
+        ModuleOptionsContext::default().cell(),
+        ResolveOptionsContext::default().cell(),
+        Vc::cell("test".to_string()),
+    );
+    let entry = module_asset_context
+        .process(
+            Vc::upcast(FileSource::new(root.join(format!("{fixture}/a.css")))),
+            Value::new(ReferenceType::Css(CssReferenceSubType::AtImport(None))),
+        )
+        .module();
+
+    let mut visited = HashSet::new();
+    let mut queue = vec![entry];
+    let mut modules = vec![];
+    while let Some(module) = queue.pop() {
+        let module = module.resolve().await?;
+        if !visited.insert(module) {
+            continue;
+        }
+        if visited.len() > MAX_MODULES {
+            bail!("the import graph of {fixture} doesn't end");
+        }
+        modules.push(module.ident().to_string().await?.clone_value());
+        for reference in module.references().await?.iter() {
+            queue.extend(
+                reference
+                    .resolve_reference()
+                    .primary_modules()
+                    .await?
+                    .iter()
+                    .copied(),
+            );
+        }
+    }
+    modules.sort();
+    Ok(Vc::cell(modules))
+}
+
+/// Returns the modules of the import graph of `fixture` and the number of
+/// reported issues.
+async fn traverse(fixture: &str) -> Result<(Vec<String>, usize)> {
+    let graph = import_graph(fixture.to_string());
+    let modules = graph.strongly_consistent().await?.clone_value();
+    let issues = graph.peek_issues_with_path().await?;
+    Ok((modules, issues.len()))
+}
+
+fn file_names(modules: &[String]) -> Vec<&str> {
+    modules
+        .iter()
+        .map(|ident| {
+            let path = ident.split(' ').next().unwrap_or(ident);
+            path.rsplit('/').next().unwrap_or(path)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn css_import_cycles() {
+    register();
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/register_test_css-import-cycles.rs"
+    ));
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.run_once(async move {
+        // `a` imports `b` in a layer, which imports `a` in a layer again. Both
+        // imports close the cycle, so their layers are ignored and the
+        // traversal ends.
+        let (modules, issues) = traverse("cycle").await?;
+        assert_eq!(file_names(&modules), ["a.css", "b.css"]);
+        assert_eq!(issues, 2);
+
+        // `a` imports `b` and `c`, which both import `d` in the same layer. `d`
+        // is shared and not part of a cycle.
+        let (modules, issues) = traverse("diamond").await?;
+        assert_eq!(file_names(&modules), ["a.css", "b.css", "c.css", "d.css"]);
+        assert_eq!(issues, 0);
+
+        Ok(())
+    })
+    .await
+    .unwrap();
+}